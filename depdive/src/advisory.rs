@@ -8,6 +8,7 @@ use rustsec::{
     database::{Database, Query},
     package::Name,
 };
+use std::path::Path;
 use std::str::FromStr;
 use tempfile::tempdir;
 
@@ -27,6 +28,14 @@ impl AdvisoryLookup {
         Ok(Self { db })
     }
 
+    /// Opens a local copy of the advisory database without fetching it,
+    /// e.g., a vendored copy for offline runs
+    pub fn from_path(path: &Path) -> Result<Self> {
+        Ok(Self {
+            db: Database::open(path)?,
+        })
+    }
+
     pub fn get_crate_version_advisories(
         &self,
         name: &str,
@@ -0,0 +1,381 @@
+//! This module loads the analysis configuration a team commits alongside its repository,
+//! i.e., a `depdive.toml` at the workspace root, in the below toml format,
+//! read from the prior state of an update, so that the update cannot change its own review,
+//! where every key is optional, and a key left out keeps the analyzer default:
+//! ```toml
+//! exclude_crates = ["diem-*"]   # crates to skip, `*` matches any sequence of characters
+//! only_crates = ["libc"]        # if set, only these crates are analyzed
+//! max_concurrent_clones = 4
+//! advisory_db_path = "advisory-db"
+//! docsrs_lookup = true
+//! largest_file_changes_count = 10  # files highlighted as the largest changes
+//! only_findings = true
+//!
+//! [registry]                    # a crates.io compatible registry, e.g., a mirror
+//! api_base_url = "https://registry.example.com/api/v1"
+//! token_env = "REGISTRY_TOKEN"  # variable holding the token, never the token itself
+//!
+//! [trusted]                     # see the trust module
+//! crate_names = ["guppy"]
+//! owners = ["rust-lang", "git.internal.com/team"]
+//! hosts = ["git.example.com"]
+//!
+//! [diff]                        # comparing crates.io code with the git source
+//! ignore_paths = ["benches/data"]
+//! adaptive_ignore_paths = true  # ignore only the registry files the crate has
+//! ignore_file_names = ["Cargo.toml.orig"]
+//! release_branches = ["release-1.x"]
+//! host_api_tag_lookup = true
+//! host_token_env = { "github.com" = "GITHUB_TOKEN" }  # variables holding host tokens
+//! max_compression_ratio = 20.0
+//! include_merge_commits = false
+//! content_only_comparison = true
+//! expected_exclude_patterns = ["/.github/"]
+//! max_benign_lines = 5          # differing lines tolerated as a near match
+//! generated_file_patterns = ["/src/generated/"]
+//! ignore_generated_code = true  # differing generated code does not make a crate differ
+//! source_extensions = { "rs" = "RustSource", "c" = "NativeSource", "py" = "ScriptSource" }
+//! ```
+//! Unknown keys are rejected, e.g., to catch a typo silently dropping an option.
+//! Options given on the command line override the ones in the file.
+
+use anyhow::{anyhow, Result};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::cratesio::Registry;
+use crate::diff::{DiffAnalyzer, FileCategory};
+use crate::trust::TrustAllowlist;
+use crate::update::UpdateAnalyzer;
+
+pub const CONFIG_FILE_NAME: &str = "depdive.toml";
+const DEFAULT_GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DepdiveConfig {
+    pub exclude_crates: Vec<String>,
+    pub only_crates: Option<Vec<String>>,
+    pub max_concurrent_clones: Option<usize>,
+    // relative to the directory of the config file
+    pub advisory_db_path: Option<PathBuf>,
+    pub docsrs_lookup: Option<bool>,
+    pub largest_file_changes_count: Option<usize>,
+    pub only_findings: Option<bool>,
+    pub registry: RegistryConfig,
+    pub trusted: TrustAllowlist,
+    pub diff: DiffConfig,
+}
+
+/// The registry to use instead of crates.io, None keeps crates.io
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistryConfig {
+    pub api_base_url: Option<String>,
+    // the environment variable holding the token, as the config file is committed
+    pub token_env: Option<String>,
+}
+
+/// Options for the diff analyzers, None keeps the analyzer default
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffConfig {
+    pub ignore_paths: Option<Vec<String>>,
+    pub adaptive_ignore_paths: Option<bool>,
+    pub ignore_file_names: Option<Vec<String>>,
+    pub release_branches: Option<Vec<String>>,
+    pub host_api_tag_lookup: Option<bool>,
+    // the environment variable holding the token of each repository host
+    pub host_token_env: Option<HashMap<String, String>>,
+    pub max_compression_ratio: Option<f64>,
+    pub include_merge_commits: Option<bool>,
+    pub content_only_comparison: Option<bool>,
+    pub expected_exclude_patterns: Option<Vec<String>>,
+    pub max_benign_lines: Option<u64>,
+    pub generated_file_patterns: Option<Vec<String>>,
+    pub ignore_generated_code: Option<bool>,
+    // replaces the default extensions counted as source, see FileCategory
+    pub source_extensions: Option<HashMap<String, FileCategory>>,
+}
+
+impl DepdiveConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::from_content(&std::fs::read_to_string(path)?, path)
+    }
+
+    // Parses the content of a config file at the given path
+    fn from_content(content: &str, path: &Path) -> Result<Self> {
+        let mut config: Self = toml::from_str(content)
+            .map_err(|e| anyhow!("invalid config in {}: {}", path.display(), e))?;
+        if let (Some(advisory_db_path), Some(config_dir)) =
+            (&config.advisory_db_path, path.parent())
+        {
+            config.advisory_db_path = Some(config_dir.join(advisory_db_path));
+        }
+        Ok(config)
+    }
+
+    /// Loads the config file at the root of a workspace, if there is one
+    pub fn from_workspace_root(workspace_root: &Path) -> Result<Option<Self>> {
+        let path = workspace_root.join(CONFIG_FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(Self::from_file(&path)?))
+    }
+
+    /// Loads the config file at the root of a repository as of a given commit,
+    /// if there is one, regardless of the checked out state
+    pub fn from_repo_commit(repo_path: &Path, commit: &str) -> Result<Option<Self>> {
+        let repo = Repository::open(repo_path)?;
+        let tree = repo.find_commit(Oid::from_str(commit)?)?.tree()?;
+        let entry = match tree.get_name(CONFIG_FILE_NAME) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let blob = entry.to_object(&repo)?.peel_to_blob()?;
+        Ok(Some(Self::from_content(
+            std::str::from_utf8(blob.content())?,
+            &repo_path.join(CONFIG_FILE_NAME),
+        )?))
+    }
+
+    pub fn is_only_findings(&self) -> bool {
+        self.only_findings.unwrap_or(false)
+    }
+
+    pub fn apply_to_update_analyzer(&self, update_analyzer: &mut UpdateAnalyzer) -> Result<()> {
+        update_analyzer.set_registry(self.registry.get_registry()?);
+        update_analyzer.set_allowlist(self.trusted.clone());
+        update_analyzer.set_exclude_patterns(&as_strs(&self.exclude_crates));
+        if let Some(only_crates) = &self.only_crates {
+            update_analyzer.set_only_crates(&as_strs(only_crates));
+        }
+        if let Some(max_concurrent_clones) = self.max_concurrent_clones {
+            update_analyzer.set_max_concurrent_clones(max_concurrent_clones);
+        }
+        if let Some(advisory_db_path) = &self.advisory_db_path {
+            update_analyzer.set_advisory_db_path(advisory_db_path);
+        }
+        if let Some(docsrs_lookup) = self.docsrs_lookup {
+            update_analyzer.set_docsrs_lookup(docsrs_lookup);
+        }
+        if let Some(largest_file_changes_count) = self.largest_file_changes_count {
+            update_analyzer.set_largest_file_changes_count(largest_file_changes_count);
+        }
+        update_analyzer.set_diff_config(self.diff.clone());
+        Ok(())
+    }
+}
+
+impl RegistryConfig {
+    /// Returns the configured registry, with the token read from its variable
+    pub fn get_registry(&self) -> Result<Registry> {
+        let mut registry = Registry::default();
+        if let Some(api_base_url) = &self.api_base_url {
+            registry.api_base_url = api_base_url.clone();
+        }
+        if let Some(token_env) = &self.token_env {
+            let token = std::env::var(token_env)
+                .map_err(|_e| anyhow!("registry token variable {} is not set", token_env))?;
+            registry.token = Some(token);
+        }
+        Ok(registry)
+    }
+}
+
+impl DiffConfig {
+    pub fn apply_to_diff_analyzer(&self, diff_analyzer: &mut DiffAnalyzer) -> Result<()> {
+        match &self.host_token_env {
+            Some(host_token_env) => {
+                for (host, token_env) in host_token_env {
+                    let token = std::env::var(token_env).map_err(|_e| {
+                        anyhow!("token variable {} of {} is not set", token_env, host)
+                    })?;
+                    diff_analyzer.set_host_token(host, &token);
+                }
+            }
+            // GITHUB_TOKEN is picked up if set, as by most tooling
+            None => {
+                if let Ok(token) = std::env::var(DEFAULT_GITHUB_TOKEN_ENV) {
+                    diff_analyzer.set_host_token("github.com", &token);
+                }
+            }
+        }
+        if let Some(ignore_paths) = &self.ignore_paths {
+            diff_analyzer.set_ignore_paths(&as_strs(ignore_paths));
+        }
+        if let Some(adaptive_ignore_paths) = self.adaptive_ignore_paths {
+            diff_analyzer.set_adaptive_ignore_paths(adaptive_ignore_paths);
+        }
+        if let Some(ignore_file_names) = &self.ignore_file_names {
+            diff_analyzer.set_ignore_file_names(&as_strs(ignore_file_names));
+        }
+        if let Some(release_branches) = &self.release_branches {
+            diff_analyzer.set_release_branches(&as_strs(release_branches));
+        }
+        if let Some(host_api_tag_lookup) = self.host_api_tag_lookup {
+            diff_analyzer.set_host_api_tag_lookup(host_api_tag_lookup);
+        }
+        if let Some(max_compression_ratio) = self.max_compression_ratio {
+            diff_analyzer.set_max_compression_ratio(max_compression_ratio);
+        }
+        if let Some(include_merge_commits) = self.include_merge_commits {
+            diff_analyzer.set_include_merge_commits(include_merge_commits);
+        }
+        if let Some(content_only_comparison) = self.content_only_comparison {
+            diff_analyzer.set_content_only_comparison(content_only_comparison);
+        }
+        if let Some(expected_exclude_patterns) = &self.expected_exclude_patterns {
+            diff_analyzer.set_expected_exclude_patterns(&as_strs(expected_exclude_patterns));
+        }
+        if let Some(max_benign_lines) = self.max_benign_lines {
+            diff_analyzer.set_max_benign_lines(max_benign_lines);
+        }
+        if let Some(generated_file_patterns) = &self.generated_file_patterns {
+            diff_analyzer.set_generated_file_patterns(&as_strs(generated_file_patterns));
+        }
+        if let Some(ignore_generated_code) = self.ignore_generated_code {
+            diff_analyzer.set_ignore_generated_code(ignore_generated_code);
+        }
+        if let Some(source_extensions) = &self.source_extensions {
+            let source_extensions: Vec<(&str, FileCategory)> = source_extensions
+                .iter()
+                .map(|(extension, category)| (extension.as_str(), *category))
+                .collect();
+            diff_analyzer.set_source_extensions(&source_extensions);
+        }
+        Ok(())
+    }
+}
+
+fn as_strs(values: &[String]) -> Vec<&str> {
+    values.iter().map(|value| value.as_str()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_config_from_workspace_root() {
+        let config = DepdiveConfig::from_workspace_root(Path::new("resources/test/config"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.exclude_crates, vec!["diem-*"]);
+        assert!(config.is_only_findings());
+        assert_eq!(
+            config.advisory_db_path,
+            Some(PathBuf::from("resources/test/config/advisory-db"))
+        );
+        assert!(config
+            .trusted
+            .is_trusted("serde", Some("https://github.com/rust-lang/serde"))
+            .unwrap());
+
+        let mut update_analyzer = UpdateAnalyzer::new();
+        config
+            .apply_to_update_analyzer(&mut update_analyzer)
+            .unwrap();
+        assert!(update_analyzer.is_excluded("diem-types").unwrap());
+        assert!(update_analyzer.is_excluded("rand").unwrap());
+        assert!(!update_analyzer.is_excluded("libc").unwrap());
+
+        let mut diff_analyzer = DiffAnalyzer::new().unwrap();
+        config
+            .diff
+            .apply_to_diff_analyzer(&mut diff_analyzer)
+            .unwrap();
+        let files: HashSet<String> = vec!["ci/run.sh".to_string(), ".github/ci.yml".to_string()]
+            .into_iter()
+            .collect();
+        let source_only_files = diff_analyzer.classify_source_only_files(&files).unwrap();
+        assert_eq!(source_only_files.expected_excludes, vec!["ci/run.sh"]);
+        assert_eq!(
+            source_only_files.unexpectedly_missing,
+            vec![".github/ci.yml"]
+        );
+        assert_eq!(
+            diff_analyzer.classify_file("build/gen.py"),
+            FileCategory::Other
+        );
+        assert_eq!(
+            diff_analyzer.classify_file("src/shim.c"),
+            FileCategory::NativeSource
+        );
+
+        assert!(
+            DepdiveConfig::from_workspace_root(Path::new("resources/test"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_config_unknown_keys() {
+        assert!(toml::from_str::<DepdiveConfig>("exclude_crate = [\"diem-*\"]").is_err());
+        assert!(toml::from_str::<DepdiveConfig>("[diff]\nignore_path = [\"a\"]").is_err());
+        assert!(toml::from_str::<DepdiveConfig>("[trusted]\nowner = [\"a\"]").is_err());
+        assert_eq!(
+            toml::from_str::<DepdiveConfig>("").unwrap(),
+            DepdiveConfig::default()
+        );
+        assert_eq!(
+            toml::from_str::<DepdiveConfig>("largest_file_changes_count = 10")
+                .unwrap()
+                .largest_file_changes_count,
+            Some(10)
+        );
+        assert_eq!(
+            toml::from_str::<DepdiveConfig>("max_concurrent_clones = 2")
+                .unwrap()
+                .max_concurrent_clones,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_config_registry() {
+        assert_eq!(
+            DepdiveConfig::default().registry.get_registry().unwrap(),
+            Registry::default()
+        );
+
+        let config: DepdiveConfig = toml::from_str(
+            "[registry]\napi_base_url = \"https://registry.example.com/api/v1\"\n\
+            token_env = \"DEPDIVE_TEST_REGISTRY_TOKEN\"",
+        )
+        .unwrap();
+        assert!(config.registry.get_registry().is_err());
+        std::env::set_var("DEPDIVE_TEST_REGISTRY_TOKEN", "secret-token");
+        assert_eq!(
+            config.registry.get_registry().unwrap(),
+            Registry {
+                api_base_url: "https://registry.example.com/api/v1".to_string(),
+                token: Some("secret-token".to_string()),
+            }
+        );
+        assert!(toml::from_str::<DepdiveConfig>("[registry]\ntoken = \"a\"").is_err());
+    }
+
+    #[test]
+    fn test_config_host_token_env() {
+        let config: DepdiveConfig = toml::from_str(
+            "[diff]\nhost_token_env = { \"git.example.com\" = \"DEPDIVE_TEST_HOST_TOKEN\" }",
+        )
+        .unwrap();
+        let mut diff_analyzer = DiffAnalyzer::new().unwrap();
+        assert!(config
+            .diff
+            .apply_to_diff_analyzer(&mut diff_analyzer)
+            .is_err());
+        std::env::set_var("DEPDIVE_TEST_HOST_TOKEN", "secret-token");
+        assert!(config
+            .diff
+            .apply_to_diff_analyzer(&mut diff_analyzer)
+            .is_ok());
+    }
+}
@@ -5,14 +5,15 @@
 // experimental database dump that is updated daily, https://crates.io/data-access,
 // which will enable us to avoid making http requests and dealing with rate limits
 
-// TODO: While we use crates_io_api crate
-// some calls are cheaper if we make http request by ourselves
-// as the crate has no direct API for our requirements and will make many extra calls
-
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
 use guppy::graph::PackageMetadata;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap};
+
+const CRATESIO_API_BASE_URL: &str = "https://crates.io/api/v1";
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct CratesioReport {
@@ -20,23 +21,85 @@ pub struct CratesioReport {
     pub is_hosted: bool,
     pub downloads: u64,
     pub dependents: u64, // Direct dependents
+    // distinct crates.io users who published the versions, sorted
+    pub publishers: Vec<String>,
+    pub multiple_publishers: bool,
+}
+
+/// A crates.io compatible registry api, e.g., an enterprise mirror,
+/// with the token, if any, sent as the `Authorization` header
+/// (e.g., "Bearer <token>" or a raw API key, as the registry expects)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Registry {
+    pub api_base_url: String,
+    pub token: Option<String>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            api_base_url: CRATESIO_API_BASE_URL.to_string(),
+            token: None,
+        }
+    }
+}
+
+impl Registry {
+    /// Returns where the tarball of a crate version is downloaded from,
+    /// e.g., https://crates.io/api/v1/crates/libc/0.2.93/download
+    pub fn get_download_url(&self, crate_name: &str, version: &str) -> String {
+        format!(
+            "{}/crates/{}/{}/download",
+            self.api_base_url.trim_end_matches('/'),
+            crate_name,
+            version
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct VersionYankStatus {
+    pub is_yanked: bool,
+    // the reason given by the publisher, None if not recorded
+    pub yank_reason: Option<String>,
 }
 
 pub struct CratesioAnalyzer {
-    crates_io_api_client: crates_io_api::SyncClient,
     http_client: reqwest::blocking::Client,
+    api_base_url: String,
+    // responses keyed by api endpoint, to avoid repeated requests within a run
+    cache: RefCell<HashMap<String, serde_json::Value>>,
 }
 
 impl CratesioAnalyzer {
     pub fn new() -> Result<Self> {
+        Self::with_registry(CRATESIO_API_BASE_URL, None)
+    }
+
+    /// Creates an analyzer against the given registry
+    pub fn for_registry(registry: &Registry) -> Result<Self> {
+        Self::with_registry(&registry.api_base_url, registry.token.as_deref())
+    }
+
+    /// Creates an analyzer against a crates.io compatible registry api,
+    /// e.g., an enterprise mirror, at the given base url.
+    /// If given, the token is sent as the `Authorization` header
+    /// (e.g., "Bearer <token>" or a raw API key, as the registry expects)
+    pub fn with_registry(api_base_url: &str, token: Option<&str>) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            let mut value = HeaderValue::from_str(token)?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+
         Ok(Self {
-            crates_io_api_client: crates_io_api::SyncClient::new(
-                "User-Agent: Whackadep (https://github.com/diem/whackadep)",
-                std::time::Duration::from_millis(1000),
-            )?,
             http_client: reqwest::blocking::Client::builder()
                 .user_agent("diem/whackadep")
+                .default_headers(headers)
                 .build()?,
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+            cache: RefCell::new(HashMap::new()),
         })
     }
 
@@ -55,24 +118,28 @@ impl CratesioAnalyzer {
             });
         }
 
-        let crate_info = self.crates_io_api_client.get_crate(name)?.crate_data;
+        let downloads = self.get_total_downloads(name)?;
         let dependents = self.get_total_dependents(name)?;
+        let publishers = self.get_publishers(name)?;
 
         let cratesio_report = CratesioReport {
             name: name.to_string(),
             is_hosted,
-            downloads: crate_info.downloads,
+            downloads,
             dependents,
+            multiple_publishers: publishers.len() > 1,
+            publishers,
         };
 
         Ok(cratesio_report)
     }
 
-    pub fn get_total_dependents(&self, crate_name: &str) -> Result<u64> {
-        let api_endpoint = format!(
-            "https://crates.io/api/v1/crates/{}/reverse_dependencies",
-            crate_name
-        );
+    // Returns the json response for an api endpoint,
+    // from the cache if already requested by this analyzer
+    fn get_json(&self, api_endpoint: &str) -> Result<serde_json::Value> {
+        if let Some(response) = self.cache.borrow().get(api_endpoint) {
+            return Ok(response.clone());
+        }
 
         let response = self.http_client.get(api_endpoint).send()?;
         if !response.status().is_success() {
@@ -80,6 +147,30 @@ impl CratesioAnalyzer {
         }
 
         let response: serde_json::Value = response.json()?;
+        self.cache
+            .borrow_mut()
+            .insert(api_endpoint.to_string(), response.clone());
+        Ok(response)
+    }
+
+    pub fn get_total_downloads(&self, crate_name: &str) -> Result<u64> {
+        let api_endpoint = format!("{}/crates/{}", self.api_base_url, crate_name);
+
+        let response = self.get_json(&api_endpoint)?;
+        let downloads: u64 = response["crate"]["downloads"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("total downloads is not an integer"))?;
+
+        Ok(downloads)
+    }
+
+    pub fn get_total_dependents(&self, crate_name: &str) -> Result<u64> {
+        let api_endpoint = format!(
+            "{}/crates/{}/reverse_dependencies",
+            self.api_base_url, crate_name
+        );
+
+        let response = self.get_json(&api_endpoint)?;
         let dependents: u64 = response["meta"]["total"]
             .as_u64()
             .ok_or_else(|| anyhow!("total dependents is not an integer"))?;
@@ -88,27 +179,152 @@ impl CratesioAnalyzer {
     }
 
     pub fn get_version_downloads(&self, crate_name: &str, version: &Version) -> Result<u64> {
-        let api_endpoint = format!("https://crates.io/api/v1/crates/{}/{}", crate_name, version);
-
-        let response = self.http_client.get(api_endpoint).send()?;
-        if !response.status().is_success() {
-            return Err(anyhow!("http request to Crates.io failed: {:?}", response));
-        }
+        let api_endpoint = format!("{}/crates/{}/{}", self.api_base_url, crate_name, version);
 
-        let response: serde_json::Value = response.json()?;
+        let response = self.get_json(&api_endpoint)?;
         let downloads: u64 = response["version"]["downloads"]
             .as_u64()
             .ok_or_else(|| anyhow!("version downloads is not an integer"))?;
 
         Ok(downloads)
     }
+
+    /// Returns the sha256 checksum the registry records for a crate version
+    pub fn get_version_checksum(&self, crate_name: &str, version: &str) -> Result<String> {
+        let api_endpoint = format!("{}/crates/{}/{}", self.api_base_url, crate_name, version);
+
+        let response = self.get_json(&api_endpoint)?;
+        response["version"]["checksum"]
+            .as_str()
+            .map(|checksum| checksum.to_string())
+            .ok_or_else(|| anyhow!("no checksum on crates.io for {} {}", crate_name, version))
+    }
+
+    /// Returns if a crate version is yanked, and why if crates.io recorded a reason
+    pub fn get_version_yank_status(
+        &self,
+        crate_name: &str,
+        version: &Version,
+    ) -> Result<VersionYankStatus> {
+        let api_endpoint = format!("{}/crates/{}/{}", self.api_base_url, crate_name, version);
+
+        let response = self.get_json(&api_endpoint)?;
+        Ok(get_yank_status_from_version(&response["version"]))
+    }
+
+    /// Returns the crates.io user who published a crate version,
+    /// None if published before crates.io recorded the publisher
+    pub fn get_version_publisher(
+        &self,
+        crate_name: &str,
+        version: &Version,
+    ) -> Result<Option<String>> {
+        let api_endpoint = format!("{}/crates/{}/{}", self.api_base_url, crate_name, version);
+
+        let response = self.get_json(&api_endpoint)?;
+        Ok(response["version"]["published_by"]["login"]
+            .as_str()
+            .map(|login| login.to_string()))
+    }
+
+    /// Returns the distinct crates.io users who published the versions of a crate,
+    /// where a change in publishers over time is a supply chain signal
+    /// Versions published before crates.io recorded the publisher are skipped
+    pub fn get_publishers(&self, crate_name: &str) -> Result<Vec<String>> {
+        let api_endpoint = format!("{}/crates/{}", self.api_base_url, crate_name);
+
+        let response = self.get_json(&api_endpoint)?;
+        let versions = response["versions"]
+            .as_array()
+            .ok_or_else(|| anyhow!("versions is not an array"))?;
+
+        let mut publishers: Vec<String> = versions
+            .iter()
+            .filter_map(|version| version["published_by"]["login"].as_str())
+            .map(|login| login.to_string())
+            .collect();
+        publishers.sort();
+        publishers.dedup();
+        Ok(publishers)
+    }
+
+    /// Returns the latest version of a crate that is not yanked,
+    /// where pre-releases, e.g., release candidates, are considered only if included
+    pub fn get_latest_version(
+        &self,
+        crate_name: &str,
+        include_prereleases: bool,
+    ) -> Result<Version> {
+        let api_endpoint = format!("{}/crates/{}", self.api_base_url, crate_name);
+
+        let response = self.get_json(&api_endpoint)?;
+        let versions = response["versions"]
+            .as_array()
+            .ok_or_else(|| anyhow!("versions is not an array"))?;
+
+        let mut candidate_versions: Vec<Version> = Vec::new();
+        for version in versions {
+            if version["yanked"].as_bool().unwrap_or(false) {
+                continue;
+            }
+            let num = version["num"]
+                .as_str()
+                .ok_or_else(|| anyhow!("version number is not a string"))?;
+            let num = Version::parse(num)?;
+            if num.is_prerelease() && !include_prereleases {
+                continue;
+            }
+            candidate_versions.push(num);
+        }
+
+        candidate_versions
+            .into_iter()
+            .max()
+            .ok_or_else(|| anyhow!("no version found for {}", crate_name))
+    }
+
+    /// Returns when a crate version was published
+    pub fn get_version_publish_time(
+        &self,
+        crate_name: &str,
+        version: &Version,
+    ) -> Result<DateTime<FixedOffset>> {
+        let api_endpoint = format!("{}/crates/{}/{}", self.api_base_url, crate_name, version);
+
+        let response = self.get_json(&api_endpoint)?;
+        let created_at = response["version"]["created_at"]
+            .as_str()
+            .ok_or_else(|| anyhow!("version publish time is not a string"))?;
+
+        Ok(DateTime::parse_from_rfc3339(created_at)?)
+    }
+}
+
+// e.g., {"num":"0.2.24","yanked":true,"yank_message":"unsound"}
+// where the reason is absent or null for versions yanked without one
+fn get_yank_status_from_version(version: &serde_json::Value) -> VersionYankStatus {
+    let is_yanked = version["yanked"].as_bool().unwrap_or(false);
+    VersionYankStatus {
+        is_yanked,
+        yank_reason: match is_yanked {
+            true => version["yank_message"]
+                .as_str()
+                .filter(|reason| !reason.trim().is_empty())
+                .map(|reason| reason.to_string()),
+            false => None,
+        },
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use guppy::MetadataCommand;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
     use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
 
     fn test_cratesio_analyzer() -> CratesioAnalyzer {
         CratesioAnalyzer::new().unwrap()
@@ -129,6 +345,7 @@ mod tests {
         assert!(report.is_hosted);
         assert!(report.downloads > 0);
         assert!(report.dependents > 0);
+        assert!(!report.publishers.is_empty());
     }
 
     #[test]
@@ -150,4 +367,220 @@ mod tests {
             .unwrap();
         assert!(downloads > 10000);
     }
+
+    // Spawns a local registry stub responding with the given body,
+    // and returns its api base url and the requests it received so far
+    fn spawn_test_registry(body: &'static str) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let received = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let received = received.clone();
+                // Serve every request on a connection, as the client keeps it alive
+                thread::spawn(move || loop {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                        match stream.read(&mut buf) {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    received
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8_lossy(&request).to_lowercase());
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .unwrap();
+                });
+            }
+        });
+
+        (format!("http://127.0.0.1:{}/api/v1/", port), requests)
+    }
+
+    #[test]
+    fn test_cratesio_authorization_header_for_registry() {
+        let (api_base_url, requests) = spawn_test_registry(r#"{"version":{"downloads":42}}"#);
+        let cratesio_analyzer =
+            CratesioAnalyzer::with_registry(&api_base_url, Some("Bearer secret-token")).unwrap();
+        let downloads = cratesio_analyzer
+            .get_version_downloads("guppy", &Version::parse("0.8.0").unwrap())
+            .unwrap();
+        assert_eq!(downloads, 42);
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("get /api/v1/crates/guppy/0.8.0 "));
+        assert!(requests[0].contains("authorization: bearer secret-token\r\n"));
+    }
+
+    #[test]
+    fn test_cratesio_registry() {
+        let (api_base_url, requests) =
+            spawn_test_registry(r#"{"version":{"num":"0.1.0","checksum":"abc123"}}"#);
+        let registry = Registry {
+            api_base_url,
+            token: Some("secret-token".to_string()),
+        };
+        let cratesio_analyzer = CratesioAnalyzer::for_registry(&registry).unwrap();
+        assert_eq!(
+            cratesio_analyzer
+                .get_version_checksum("mirrored", "0.1.0")
+                .unwrap(),
+            "abc123"
+        );
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("get /api/v1/crates/mirrored/0.1.0 "));
+        assert!(requests[0].contains("authorization: secret-token\r\n"));
+
+        assert!(registry
+            .get_download_url("mirrored", "0.1.0")
+            .ends_with("/api/v1/crates/mirrored/0.1.0/download"));
+        assert_eq!(
+            Registry::default().get_download_url("libc", "0.2.93"),
+            "https://crates.io/api/v1/crates/libc/0.2.93/download"
+        );
+    }
+
+    #[test]
+    fn test_cratesio_cached_lookups() {
+        let (api_base_url, requests) = spawn_test_registry(r#"{"crate":{"downloads":42}}"#);
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+
+        assert_eq!(cratesio_analyzer.get_total_downloads("guppy").unwrap(), 42);
+        assert_eq!(cratesio_analyzer.get_total_downloads("guppy").unwrap(), 42);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // A different crate is a different endpoint
+        assert_eq!(cratesio_analyzer.get_total_downloads("libc").unwrap(), 42);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_cratesio_publishers() {
+        let (api_base_url, _requests) = spawn_test_registry(
+            r#"{"versions":[
+                {"num":"1.0.0","published_by":{"id":1,"login":"alexcrichton"}},
+                {"num":"0.1.10","published_by":{"id":1,"login":"alexcrichton"}},
+                {"num":"0.1.0","published_by":null}
+            ]}"#,
+        );
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+        assert_eq!(
+            cratesio_analyzer.get_publishers("cfg-if").unwrap(),
+            vec!["alexcrichton".to_string()]
+        );
+
+        let (api_base_url, _requests) = spawn_test_registry(
+            r#"{"versions":[
+                {"num":"0.2.0","published_by":{"id":2,"login":"new-account"}},
+                {"num":"0.1.0","published_by":{"id":1,"login":"maintainer"}}
+            ]}"#,
+        );
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+        assert_eq!(
+            cratesio_analyzer.get_publishers("churned").unwrap(),
+            vec!["maintainer".to_string(), "new-account".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_cratesio_version_publisher() {
+        let (api_base_url, requests) = spawn_test_registry(
+            r#"{"version":{"num":"1.0.0","published_by":{"id":2,"login":"new-account"}}}"#,
+        );
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+        assert_eq!(
+            cratesio_analyzer
+                .get_version_publisher("churned", &Version::parse("1.0.0").unwrap())
+                .unwrap(),
+            Some("new-account".to_string())
+        );
+        assert!(requests.lock().unwrap()[0].contains("/crates/churned/1.0.0"));
+
+        let (api_base_url, _requests) =
+            spawn_test_registry(r#"{"version":{"num":"0.1.0","published_by":null}}"#);
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+        assert_eq!(
+            cratesio_analyzer
+                .get_version_publisher("churned", &Version::parse("0.1.0").unwrap())
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cratesio_latest_version() {
+        let (api_base_url, _requests) = spawn_test_registry(
+            r#"{"versions":[
+                {"num":"1.0.0-rc.2","yanked":false},
+                {"num":"1.0.0-rc.1","yanked":false},
+                {"num":"0.14.1","yanked":true},
+                {"num":"0.14.0","yanked":false},
+                {"num":"0.9.0","yanked":false}
+            ]}"#,
+        );
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+        assert_eq!(
+            cratesio_analyzer
+                .get_latest_version("hyper", false)
+                .unwrap(),
+            Version::parse("0.14.0").unwrap()
+        );
+        assert_eq!(
+            cratesio_analyzer.get_latest_version("hyper", true).unwrap(),
+            Version::parse("1.0.0-rc.2").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cratesio_version_yank_status() {
+        let (api_base_url, _requests) = spawn_test_registry(include_str!(
+            "../resources/test/cratesio_yanked_version_response.json"
+        ));
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+        let yank_status = cratesio_analyzer
+            .get_version_yank_status("time", &Version::parse("0.2.24").unwrap())
+            .unwrap();
+        assert!(yank_status.is_yanked);
+        assert_eq!(
+            yank_status.yank_reason.as_deref(),
+            Some("unsound use of localtime_r, upgrade to 0.2.25")
+        );
+
+        assert_eq!(
+            get_yank_status_from_version(&serde_json::json!({"yanked": true})),
+            VersionYankStatus {
+                is_yanked: true,
+                yank_reason: None
+            }
+        );
+        assert_eq!(
+            get_yank_status_from_version(
+                &serde_json::json!({"yanked": false, "yank_message": null})
+            ),
+            VersionYankStatus::default()
+        );
+    }
+
+    #[test]
+    fn test_cratesio_version_publish_time() {
+        let (api_base_url, _requests) = spawn_test_registry(
+            r#"{"version":{"num":"0.9.0","created_at":"2021-03-10T21:47:15.543421+00:00"}}"#,
+        );
+        let cratesio_analyzer = CratesioAnalyzer::with_registry(&api_base_url, None).unwrap();
+        let publish_time = cratesio_analyzer
+            .get_version_publish_time("guppy", &Version::parse("0.9.0").unwrap())
+            .unwrap();
+        assert_eq!(publish_time.timestamp(), 1615412835);
+    }
 }
@@ -0,0 +1,204 @@
+//! This module abstracts the communication with docs.rs for a given crate version
+//! and checks the documented features against the enabled ones
+
+use anyhow::Result;
+use reqwest::StatusCode;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::super_toml::{CargoTomlParser, DocsrsMetadata};
+
+const DOCSRS_BASE_URL: &str = "https://docs.rs";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DocsrsReport {
+    pub name: String,
+    pub version: Version,
+    pub build_succeeded: Option<bool>, // None if docs.rs has not built the version
+    // docs.rs build configuration declared in the manifest
+    pub metadata: DocsrsMetadata,
+    // enabled features whose api is not covered in the docs
+    pub undocumented_features: Vec<String>,
+}
+
+pub struct DocsrsAnalyzer {
+    http_client: reqwest::blocking::Client,
+    base_url: String,
+}
+
+impl DocsrsAnalyzer {
+    pub fn new() -> Result<Self> {
+        Self::with_base_url(DOCSRS_BASE_URL)
+    }
+
+    /// Creates an analyzer against a docs.rs compatible host at the given base url
+    pub fn with_base_url(base_url: &str) -> Result<Self> {
+        Ok(Self {
+            http_client: reqwest::blocking::Client::builder()
+                .user_agent("diem/whackadep")
+                .build()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Returns the docs.rs report for a crate version,
+    /// given its published manifest and the features enabled for it
+    pub fn get_docsrs_report(
+        &self,
+        name: &str,
+        version: &Version,
+        toml_parser: &CargoTomlParser,
+        enabled_features: &[&str],
+    ) -> Result<DocsrsReport> {
+        let mut docsrs_report =
+            get_offline_docsrs_report(name, version, toml_parser, enabled_features)?;
+        docsrs_report.build_succeeded = self.get_build_status(name, version)?;
+        Ok(docsrs_report)
+    }
+
+    /// Returns if the docs.rs build of a crate version succeeded,
+    /// or None if docs.rs has no build for the version
+    pub fn get_build_status(&self, name: &str, version: &Version) -> Result<Option<bool>> {
+        let endpoint = format!("{}/crate/{}/{}/status.json", self.base_url, name, version);
+        let response = self.http_client.get(&endpoint).send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response: serde_json::Value = response.error_for_status()?.json()?;
+        Ok(get_build_status_from_response(&response))
+    }
+}
+
+/// Returns the docs.rs report for a crate version from its published manifest
+/// and the features enabled for it, without looking up the build on docs.rs
+pub fn get_offline_docsrs_report(
+    name: &str,
+    version: &Version,
+    toml_parser: &CargoTomlParser,
+    enabled_features: &[&str],
+) -> Result<DocsrsReport> {
+    let metadata = toml_parser.get_docsrs_metadata()?;
+    let undocumented_features = get_undocumented_features(
+        &metadata,
+        &toml_parser.get_feature_graph()?,
+        enabled_features,
+    );
+    Ok(DocsrsReport {
+        name: name.to_string(),
+        version: version.clone(),
+        build_succeeded: None,
+        metadata,
+        undocumented_features,
+    })
+}
+
+// e.g., {"doc_status":true,"version":"0.2.93"}
+fn get_build_status_from_response(response: &serde_json::Value) -> Option<bool> {
+    response["doc_status"].as_bool()
+}
+
+/// Returns the enabled features that the docs.rs build does not enable,
+/// directly or through the features it enables, sorted
+pub fn get_undocumented_features(
+    metadata: &DocsrsMetadata,
+    feature_graph: &HashMap<String, Vec<String>>,
+    enabled_features: &[&str],
+) -> Vec<String> {
+    if metadata.all_features {
+        return Vec::new();
+    }
+
+    let mut documented: HashSet<&str> = HashSet::new();
+    let mut to_visit: Vec<&str> = metadata.features.iter().map(|f| f.as_str()).collect();
+    if !metadata.no_default_features {
+        to_visit.push("default");
+    }
+    while let Some(feature) = to_visit.pop() {
+        // e.g., "dep:serde" or "serde/std" enable the feature of serde, if optional
+        let feature = feature.trim_start_matches("dep:");
+        let feature = feature.split('/').next().unwrap_or(feature);
+        let feature = feature.trim_end_matches('?');
+        if documented.insert(feature) {
+            if let Some(enables) = feature_graph.get(feature) {
+                to_visit.extend(enables.iter().map(|f| f.as_str()));
+            }
+        }
+    }
+
+    let mut undocumented_features: Vec<String> = enabled_features
+        .iter()
+        .filter(|feature| !documented.contains(*feature))
+        .map(|feature| feature.to_string())
+        .collect();
+    undocumented_features.sort();
+    undocumented_features.dedup();
+    undocumented_features
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use indoc::indoc;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn test_docsrs_build_status_from_response() {
+        let response: serde_json::Value = serde_json::from_str(
+            &read_to_string("resources/test/docsrs_status_response.json").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(get_build_status_from_response(&response), Some(true));
+        assert_eq!(
+            get_build_status_from_response(&serde_json::json!({"doc_status": false})),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_docsrs_undocumented_features() {
+        let toml_parser = CargoTomlParser::from_content(
+            indoc! {r#"
+                [package]
+                name = "a"
+                version = "0.1.0"
+
+                [features]
+                default = ["std"]
+                std = []
+                full = ["net", "fs"]
+                net = []
+                fs = []
+                unstable = []
+
+                [dependencies]
+                serde = { version = "1", optional = true }
+
+                [package.metadata.docs.rs]
+                features = ["full"]
+                targets = ["x86_64-unknown-linux-gnu"]
+            "#},
+            "Cargo.toml",
+        )
+        .unwrap();
+        let metadata = toml_parser.get_docsrs_metadata().unwrap();
+        assert_eq!(metadata.features, vec!["full"]);
+        assert_eq!(metadata.targets, vec!["x86_64-unknown-linux-gnu"]);
+
+        let feature_graph = toml_parser.get_feature_graph().unwrap();
+        assert_eq!(
+            get_undocumented_features(
+                &metadata,
+                &feature_graph,
+                &["std", "net", "serde", "unstable"]
+            ),
+            vec!["serde", "unstable"]
+        );
+
+        let metadata = DocsrsMetadata {
+            all_features: true,
+            ..Default::default()
+        };
+        assert!(get_undocumented_features(&metadata, &feature_graph, &["unstable"]).is_empty());
+    }
+}
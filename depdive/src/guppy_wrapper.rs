@@ -36,6 +36,37 @@ pub(crate) fn get_all_dependencies(graph: &PackageGraph) -> Vec<PackageMetadata>
         .collect()
 }
 
+/// Returns the dependencies across multiple graphs, e.g., of independent workspaces,
+/// deduplicated by crate name and version
+pub(crate) fn get_all_dependencies_across_graphs(
+    graphs: &[PackageGraph],
+) -> Vec<PackageMetadata<'_>> {
+    let mut seen: HashSet<(&str, &Version)> = HashSet::new();
+    graphs
+        .iter()
+        .flat_map(get_all_dependencies)
+        .filter(|pkg| seen.insert((pkg.name(), pkg.version())))
+        .collect()
+}
+
+/// Returns the crates present at more than one version in the dependencies,
+/// along with their versions in ascending order
+pub(crate) fn get_duplicate_versions(graph: &PackageGraph) -> HashMap<String, Vec<Version>> {
+    let mut versions: HashMap<String, Vec<Version>> = HashMap::new();
+    for pkg in get_all_dependencies(graph) {
+        let pkg_versions = versions.entry(pkg.name().to_string()).or_default();
+        if !pkg_versions.contains(pkg.version()) {
+            pkg_versions.push(pkg.version().clone());
+        }
+    }
+
+    versions.retain(|_name, versions| versions.len() > 1);
+    for pkg_versions in versions.values_mut() {
+        pkg_versions.sort();
+    }
+    versions
+}
+
 pub(crate) fn get_package_dependencies<'a>(
     graph: &'a PackageGraph,
     package: &PackageMetadata,
@@ -196,6 +227,66 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_guppy_wrapper_duplicate_versions() {
+        let metadata = CargoMetadata::parse_json(include_str!(
+            "../resources/test/post_conflict_metadata.json"
+        ))
+        .unwrap();
+        let graph = metadata.build_graph().unwrap();
+        let duplicate_versions = get_duplicate_versions(&graph);
+
+        assert_eq!(
+            duplicate_versions.get("target-spec").unwrap(),
+            &vec![
+                Version::parse("0.6.1").unwrap(),
+                Version::parse("0.7.0").unwrap()
+            ]
+        );
+        assert!(!duplicate_versions.contains_key("guppy"));
+        assert!(duplicate_versions
+            .values()
+            .all(|versions| versions.len() > 1));
+    }
+
+    #[test]
+    fn test_guppy_wrapper_dependencies_across_graphs() {
+        let graphs: Vec<PackageGraph> = [
+            include_str!("../resources/test/depkind_metadata.json"),
+            include_str!("../resources/test/exclusive_dep_cargo_metadata.json"),
+        ]
+        .iter()
+        .map(|json| {
+            CargoMetadata::parse_json(json)
+                .unwrap()
+                .build_graph()
+                .unwrap()
+        })
+        .collect();
+
+        let first: HashSet<(String, Version)> = get_all_dependencies(&graphs[0])
+            .iter()
+            .map(|pkg| (pkg.name().to_string(), pkg.version().clone()))
+            .collect();
+        let second: HashSet<(String, Version)> = get_all_dependencies(&graphs[1])
+            .iter()
+            .map(|pkg| (pkg.name().to_string(), pkg.version().clone()))
+            .collect();
+        let shared = first.intersection(&second).count();
+        assert!(shared > 0);
+
+        let merged: Vec<(String, Version)> = get_all_dependencies_across_graphs(&graphs)
+            .iter()
+            .map(|pkg| (pkg.name().to_string(), pkg.version().clone()))
+            .collect();
+        let unique: HashSet<&(String, Version)> = merged.iter().collect();
+        assert_eq!(merged.len(), unique.len());
+        assert_eq!(merged.len(), first.len() + second.len() - shared);
+        assert!(merged
+            .iter()
+            .all(|dep| first.contains(dep) || second.contains(dep)));
+    }
+
     #[test]
     fn test_guppy_wrapper_dep_kind() {
         let metadata =
@@ -36,25 +36,37 @@ use guppy::MetadataCommand;
 use semver::Version;
 use separator::Separatable;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::str::FromStr;
 
 pub mod advisory;
 pub mod code;
+pub mod config;
 pub mod cratesio;
 pub mod diff;
+pub mod docsrs;
 pub mod ghcomment;
 pub mod github;
 mod guppy_wrapper;
+pub mod ossf;
+pub mod sink;
+pub mod source;
 pub mod super_toml;
+pub mod trust;
 pub mod update;
 
-use cratesio::CratesioReport;
+use config::DepdiveConfig;
+use cratesio::{CratesioReport, Registry, VersionYankStatus};
+use diff::DefaultFeaturesChange;
 use ghcomment::{Emoji::*, GitHubCommentGenerator, TextStyle::*};
 use github::GitHubReport;
 use guppy_wrapper::{
-    get_all_dependencies, get_dep_kind_map, get_direct_dependencies, DependencyKind,
+    get_all_dependencies_across_graphs, get_dep_kind_map, get_direct_dependencies,
+    get_duplicate_versions, DependencyKind,
 };
+use sink::{FindingsOnlySink, OutputSink};
+use trust::TrustAllowlist;
 use update::{CrateVersionRustSecAdvisory, UpdateReviewReport, VersionConflict};
 
 /// Usage and Activity metrics for a crate
@@ -70,25 +82,51 @@ pub struct PackageMetrics {
 pub struct DependencyAnalyzer;
 
 impl DependencyAnalyzer {
-    /// Given a cargo project path, outputs usage and activity metrics
+    /// Given a cargo project path, outputs usage and activity metrics,
+    /// with the crates looked up in the given registry
     pub fn get_dep_package_metrics_in_json_from_path(
         path: &Path,
         only_direct: bool,
+        registry: &Registry,
     ) -> Result<String> {
-        let graph = MetadataCommand::new().current_dir(path).build_graph()?;
-        Self::get_dep_package_metrics_in_json(&graph, only_direct)
+        Self::get_dep_package_metrics_in_json_from_paths(&[path], only_direct, registry)
+    }
+
+    /// Given multiple cargo project paths or manifest paths,
+    /// e.g., of independent workspaces in a monorepo,
+    /// outputs usage and activity metrics for their dependencies
+    /// deduplicated by crate name and version
+    pub fn get_dep_package_metrics_in_json_from_paths(
+        paths: &[&Path],
+        only_direct: bool,
+        registry: &Registry,
+    ) -> Result<String> {
+        let graphs = paths
+            .iter()
+            .map(|path| build_graph_from_path(path))
+            .collect::<Result<Vec<PackageGraph>>>()?;
+        Self::get_dep_package_metrics_in_json(&graphs, only_direct, registry)
     }
 
-    /// Given a guppy graph, outputs usage and activity metrics
-    fn get_dep_package_metrics_in_json(graph: &PackageGraph, only_direct: bool) -> Result<String> {
+    /// Given guppy graphs, outputs usage and activity metrics
+    fn get_dep_package_metrics_in_json(
+        graphs: &[PackageGraph],
+        only_direct: bool,
+        registry: &Registry,
+    ) -> Result<String> {
         let mut output: Vec<PackageMetrics> = Vec::new();
 
-        let all_deps = get_all_dependencies(graph);
-        let direct_deps: HashSet<(&str, &Version)> = get_direct_dependencies(graph)
+        let all_deps = get_all_dependencies_across_graphs(graphs);
+        let direct_deps: HashSet<(&str, &Version)> = graphs
             .iter()
+            .flat_map(get_direct_dependencies)
             .map(|pkg| (pkg.name(), pkg.version()))
             .collect();
-        let dep_kind_map = get_dep_kind_map(graph)?;
+        // A dep shared across graphs takes its kind from the first graph it appears in
+        let mut dep_kind_map = HashMap::new();
+        for graph in graphs.iter().rev() {
+            dep_kind_map.extend(get_dep_kind_map(graph)?);
+        }
 
         for dep in &all_deps {
             let is_direct = direct_deps.contains(&(dep.name(), dep.version()));
@@ -106,7 +144,7 @@ impl DependencyAnalyzer {
                 })?
                 .clone();
 
-            let cratesio_metrics = cratesio::CratesioAnalyzer::new()?;
+            let cratesio_metrics = cratesio::CratesioAnalyzer::for_registry(registry)?;
             let cratesio_metrics: Option<CratesioReport> =
                 cratesio_metrics.analyze_cratesio(dep).ok();
 
@@ -128,6 +166,17 @@ impl DependencyAnalyzer {
     }
 }
 
+/// Builds a guppy graph from either a cargo project path or a Cargo.toml path
+fn build_graph_from_path(path: &Path) -> Result<PackageGraph> {
+    let mut command = MetadataCommand::new();
+    if path.is_file() {
+        command.manifest_path(path);
+    } else {
+        command.current_dir(path);
+    }
+    Ok(command.build_graph()?)
+}
+
 pub struct DependencyGraphAnalyzer;
 
 impl DependencyGraphAnalyzer {
@@ -137,6 +186,12 @@ impl DependencyGraphAnalyzer {
         Self::get_code_metrics_in_json(&graph, only_direct)
     }
 
+    /// Given a guppy graph, returns the crates present at more than one version,
+    /// i.e., candidates for consolidation to cut supply chain surface and binary size
+    pub fn duplicate_versions(graph: &PackageGraph) -> HashMap<String, Vec<Version>> {
+        get_duplicate_versions(graph)
+    }
+
     /// Given a guppy graph, outputs loc and unsafe loc metrics
     fn get_code_metrics_in_json(graph: &PackageGraph, only_direct: bool) -> Result<String> {
         let code_reports = code::CodeAnalyzer::new();
@@ -162,6 +217,31 @@ pub enum AdvisoryStatus {
              // before and after of an update
 }
 
+/// Output format of the update review report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,          // a summary to post as a comment, e.g., on pull requests
+    Ndjson,            // one json object per updated dependency on each line
+    GitHubAnnotations, // GitHub Actions workflow commands for per-file findings
+    Prometheus,        // batch and per-crate gauges in the Prometheus text exposition format
+    Table,             // a row per updated dependency for scanning in a terminal
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "markdown" => Ok(Self::Markdown),
+            "ndjson" => Ok(Self::Ndjson),
+            "github-annotations" => Ok(Self::GitHubAnnotations),
+            "prometheus" => Ok(Self::Prometheus),
+            "table" => Ok(Self::Table),
+            _ => Err(anyhow!("unknown report format: {}", s)),
+        }
+    }
+}
+
 pub struct UpdateAnalyzer;
 
 impl UpdateAnalyzer {
@@ -171,7 +251,38 @@ impl UpdateAnalyzer {
         prior_graph: &PackageGraph,
         post_graph: &PackageGraph,
     ) -> Result<UpdateReviewReport> {
-        let update_analyzer = update::UpdateAnalyzer::new();
+        Self::run_update_analyzer_with_allowlist(
+            prior_graph,
+            post_graph,
+            &TrustAllowlist::default(),
+        )
+    }
+
+    /// Given two guppy graph, prior and post,
+    /// Analyzed the updated dependencies
+    /// while marking the crates trusted by the allowlist
+    pub fn run_update_analyzer_with_allowlist(
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+        allowlist: &TrustAllowlist,
+    ) -> Result<UpdateReviewReport> {
+        let mut update_analyzer = update::UpdateAnalyzer::new();
+        update_analyzer.set_allowlist(allowlist.clone());
+        update_analyzer.analyze_updates(prior_graph, post_graph)
+    }
+
+    /// Given two guppy graph, prior and post,
+    /// Analyzed the updated dependencies without reaching crates.io,
+    /// from their sources already on disk, e.g., in the local registry cache
+    pub fn run_update_analyzer_offline(
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+    ) -> Result<UpdateReviewReport> {
+        let mut update_analyzer = update::UpdateAnalyzer::new();
+        update_analyzer.set_offline(Box::new(source::LocalSourceProvider::new(&[
+            prior_graph,
+            post_graph,
+        ])));
         update_analyzer.analyze_updates(prior_graph, post_graph)
     }
 
@@ -182,9 +293,30 @@ impl UpdateAnalyzer {
         prior_graph: &PackageGraph,
         post_graph: &PackageGraph,
     ) -> Result<Option<String>> {
-        let update_review_report = Self::run_update_analyzer(prior_graph, post_graph)?;
+        Self::get_summary_report_with_allowlist(prior_graph, post_graph, &TrustAllowlist::default())
+    }
+
+    /// Same as `get_summary_report`, except that
+    /// updates of crates trusted by the allowlist are auto-passed
+    /// unless there is a hard signal (see the trust module)
+    pub fn get_summary_report_with_allowlist(
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+        allowlist: &TrustAllowlist,
+    ) -> Result<Option<String>> {
+        let update_review_report =
+            Self::run_update_analyzer_with_allowlist(prior_graph, post_graph, allowlist)?;
+        Self::get_markdown_report(&update_review_report)
+    }
+
+    /// Outputs a markdown formatted report of an update review,
+    /// or None if there is no update to report
+    pub fn get_markdown_report(
+        update_review_report: &UpdateReviewReport,
+    ) -> Result<Option<String>> {
         if update_review_report.dep_update_review_reports.is_empty()
             && update_review_report.version_conflicts.is_empty()
+            && update_review_report.omitted_clean_crates.is_empty()
         {
             return Ok(None);
         }
@@ -196,15 +328,37 @@ impl UpdateAnalyzer {
 
         // Write down info on updated dependencies
         gh.add_header("Dependency update review", 2);
+        gh.add_text(&update_review_report.get_batch_summary().to_line(), &Italic);
+        gh.add_newline(2);
+        if !update_review_report.omitted_clean_crates.is_empty() {
+            gh.add_text(
+                &format!(
+                    "{} clean updates omitted: {}",
+                    update_review_report.omitted_clean_crates.len(),
+                    update_review_report.omitted_clean_crates.join(", ")
+                ),
+                &Plain,
+            );
+            gh.add_newline(2);
+        }
         for report in &update_review_report.dep_update_review_reports {
             // Version update info
+            let prerelease_note = match report.get_semver_update_kind() {
+                update::SemverUpdateKind::PreRelease => " (pre-release)",
+                _ => "",
+            };
             gh.add_header(
                 &format!(
-                    "{} updated: {} --> {}",
-                    report.name, report.prior_version.version, report.updated_version.version
+                    "{} updated: {} --> {}{}",
+                    report.name,
+                    report.prior_version.version,
+                    report.updated_version.version,
+                    prerelease_note
                 ),
                 3,
             );
+            gh.add_text(&report.summarize(), &Plain);
+            gh.add_newline(2);
 
             // Advisory
             let mut details: String = String::new();
@@ -214,6 +368,26 @@ impl UpdateAnalyzer {
                     report.updated_version.known_advisories.is_empty(),
                 ),
             ]];
+            if let Some(docsrs_report) = &report.updated_version.docsrs_report {
+                if let Some(build_succeeded) = docsrs_report.build_succeeded {
+                    checkmark_table.push(vec![
+                        "The docs.rs build succeeded",
+                        GitHubCommentGenerator::get_checkmark(build_succeeded),
+                    ]);
+                }
+                checkmark_table.push(vec![
+                    "The docs.rs build covers the enabled features",
+                    GitHubCommentGenerator::get_checkmark(
+                        docsrs_report.undocumented_features.is_empty(),
+                    ),
+                ]);
+            }
+            if let Some(yank_status) = &report.updated_version.yank_status {
+                checkmark_table.push(vec![
+                    "The updated version is not yanked",
+                    GitHubCommentGenerator::get_checkmark(!yank_status.is_yanked),
+                ]);
+            }
 
             // Keep track of advisory_highlights
 
@@ -282,6 +456,28 @@ impl UpdateAnalyzer {
                 gh.add_bulleted_list(&fixed_advisories, &Plain);
             }
 
+            if let Some(VersionYankStatus {
+                is_yanked: true,
+                yank_reason,
+            }) = &report.updated_version.yank_status
+            {
+                gh.add_header(":warning: The updated version is yanked", 3);
+                if let Some(yank_reason) = yank_reason {
+                    gh.add_text(yank_reason, &Plain);
+                    gh.add_newline(2);
+                }
+            }
+
+            if let Some(docsrs_report) = &report.updated_version.docsrs_report {
+                if !docsrs_report.undocumented_features.is_empty() {
+                    gh.add_header(
+                        ":warning: The docs.rs build does not cover the enabled features",
+                        3,
+                    );
+                    gh.add_bulleted_list(&docsrs_report.undocumented_features, &Code);
+                }
+            }
+
             // Diff summary
             match &report.diff_stats {
                 None => checkmark_table.push(vec![
@@ -315,6 +511,175 @@ impl UpdateAnalyzer {
                         &GitHubCommentGenerator::get_bulleted_list(&changed_file_paths, &Code),
                     ));
 
+                    if !stats.non_substantive_changes.is_empty() {
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show files changed only in formatting or item order",
+                            &GitHubCommentGenerator::get_bulleted_list(
+                                &stats.non_substantive_changes,
+                                &Code,
+                            ),
+                        ));
+                    }
+
+                    if !stats.untested_changed_files.is_empty() {
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show changed source files with no related tests",
+                            &GitHubCommentGenerator::get_bulleted_list(
+                                &stats.untested_changed_files,
+                                &Code,
+                            ),
+                        ));
+                    }
+
+                    let changed_rust_modules: Vec<String> = stats
+                        .changed_rust_modules
+                        .iter()
+                        .map(|(path, module_path)| {
+                            format!(
+                                "{} ({})",
+                                GitHubCommentGenerator::get_text(module_path, &Code),
+                                path
+                            )
+                        })
+                        .collect();
+                    if !changed_rust_modules.is_empty() {
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show changed rust modules",
+                            &GitHubCommentGenerator::get_bulleted_list(
+                                &changed_rust_modules,
+                                &Plain,
+                            ),
+                        ));
+                    }
+
+                    let largest_file_changes: Vec<String> = stats
+                        .largest_file_changes
+                        .iter()
+                        .map(|(path, change)| {
+                            format!(
+                                "{} (+{}/-{})",
+                                GitHubCommentGenerator::get_text(path, &Code),
+                                change.insertions.separated_string(),
+                                change.deletions.separated_string()
+                            )
+                        })
+                        .collect();
+                    if !largest_file_changes.is_empty() {
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show the files with the largest changes to review first",
+                            &GitHubCommentGenerator::get_bulleted_list(
+                                &largest_file_changes,
+                                &Plain,
+                            ),
+                        ));
+                    }
+
+                    checkmark_table.push(vec![
+                        "No change in cfg or nightly feature attributes",
+                        GitHubCommentGenerator::get_checkmark(stats.directive_changes.is_empty()),
+                    ]);
+                    if !stats.directive_changes.is_empty() {
+                        let directive_changes: Vec<String> = stats
+                            .directive_changes
+                            .iter()
+                            .flat_map(|change| {
+                                let added = change
+                                    .added_cfgs
+                                    .iter()
+                                    .chain(change.added_features.iter())
+                                    .map(move |d| (change, "added", d));
+                                let removed = change
+                                    .removed_cfgs
+                                    .iter()
+                                    .chain(change.removed_features.iter())
+                                    .map(move |d| (change, "removed", d));
+                                added.chain(removed)
+                            })
+                            .map(|(change, action, directive)| {
+                                format!(
+                                    "{} {} in {}",
+                                    GitHubCommentGenerator::get_text(directive, &Code),
+                                    action,
+                                    GitHubCommentGenerator::get_text(&change.file, &Code)
+                                )
+                            })
+                            .collect();
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show changed cfg and nightly feature attributes",
+                            &GitHubCommentGenerator::get_bulleted_list(&directive_changes, &Plain),
+                        ));
+                    }
+
+                    checkmark_table.push(vec![
+                        "No change in public API signatures",
+                        GitHubCommentGenerator::get_checkmark(!stats.public_api_changed),
+                    ]);
+                    if stats.public_api_changed {
+                        let public_api_changes: Vec<String> = stats
+                            .public_api_changes
+                            .iter()
+                            .flat_map(|change| {
+                                let added =
+                                    change.added_items.iter().map(move |i| (change, "added", i));
+                                let removed = change
+                                    .removed_items
+                                    .iter()
+                                    .map(move |i| (change, "removed", i));
+                                added.chain(removed)
+                            })
+                            .map(|(change, action, item)| {
+                                format!(
+                                    "{} {} in {}",
+                                    GitHubCommentGenerator::get_text(item, &Code),
+                                    action,
+                                    GitHubCommentGenerator::get_text(&change.file, &Code)
+                                )
+                            })
+                            .collect();
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show changed public API signatures",
+                            &GitHubCommentGenerator::get_bulleted_list(&public_api_changes, &Plain),
+                        ));
+                    }
+
+                    checkmark_table.push(vec![
+                        "No change in macro definitions",
+                        GitHubCommentGenerator::get_checkmark(stats.macro_changes.is_empty()),
+                    ]);
+                    if !stats.macro_changes.is_empty() {
+                        let macro_changes: Vec<String> = stats
+                            .macro_changes
+                            .iter()
+                            .flat_map(|change| {
+                                let added = change
+                                    .added_macros
+                                    .iter()
+                                    .map(move |m| (change, "added", m));
+                                let removed = change
+                                    .removed_macros
+                                    .iter()
+                                    .map(move |m| (change, "removed", m));
+                                let modified = change
+                                    .modified_macros
+                                    .iter()
+                                    .map(move |m| (change, "modified", m));
+                                added.chain(removed).chain(modified)
+                            })
+                            .map(|(change, action, name)| {
+                                format!(
+                                    "{} {} in {}",
+                                    GitHubCommentGenerator::get_text(&format!("{}!", name), &Code),
+                                    action,
+                                    GitHubCommentGenerator::get_text(&change.file, &Code)
+                                )
+                            })
+                            .collect();
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show changed macro definitions",
+                            &GitHubCommentGenerator::get_bulleted_list(&macro_changes, &Plain),
+                        ));
+                    }
+
                     checkmark_table.push(vec![
                         "No change in the build script",
                         GitHubCommentGenerator::get_checkmark(
@@ -330,6 +695,130 @@ impl UpdateAnalyzer {
                         ));
                     }
 
+                    checkmark_table.push(vec![
+                        "No increase in the minimum supported Rust version",
+                        GitHubCommentGenerator::get_checkmark(!stats.msrv_increased),
+                    ]);
+
+                    if let Some(dependency_tree_unchanged) = stats.dependency_tree_unchanged {
+                        checkmark_table.push(vec![
+                            "No change in declared dependencies",
+                            GitHubCommentGenerator::get_checkmark(dependency_tree_unchanged),
+                        ]);
+                    }
+
+                    checkmark_table.push(vec![
+                        "No change in default features",
+                        GitHubCommentGenerator::get_checkmark(
+                            stats.default_features_changes.is_empty(),
+                        ),
+                    ]);
+                    if !stats.default_features_changes.is_empty() {
+                        let default_features_changes: Vec<String> = stats
+                            .default_features_changes
+                            .iter()
+                            .map(|change| match change {
+                                DefaultFeaturesChange::DefaultFeature { old, new } => format!(
+                                    "default feature enables [{}], previously [{}]",
+                                    new.join(", "),
+                                    old.join(", ")
+                                ),
+                                DefaultFeaturesChange::DependencyDefaultFeatures {
+                                    target,
+                                    kind,
+                                    name,
+                                    enabled,
+                                } => format!(
+                                    "{} default features of {}{} in {}",
+                                    if *enabled { "enabled" } else { "disabled" },
+                                    GitHubCommentGenerator::get_text(name, &Code),
+                                    target
+                                        .as_ref()
+                                        .map(|target| format!(" for {}", target))
+                                        .unwrap_or_default(),
+                                    kind
+                                ),
+                            })
+                            .collect();
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show changes in default features",
+                            &GitHubCommentGenerator::get_bulleted_list(
+                                &default_features_changes,
+                                &Plain,
+                            ),
+                        ));
+                    }
+
+                    checkmark_table.push(vec![
+                        "No new network endpoints in added code",
+                        GitHubCommentGenerator::get_checkmark(
+                            stats.new_network_endpoints.is_empty(),
+                        ),
+                    ]);
+                    if !stats.new_network_endpoints.is_empty() {
+                        let new_network_endpoints: Vec<String> = stats
+                            .new_network_endpoints
+                            .iter()
+                            .map(|endpoint| {
+                                format!(
+                                    "{} in {}",
+                                    GitHubCommentGenerator::get_text(&endpoint.endpoint, &Code),
+                                    endpoint.locations.join(", ")
+                                )
+                            })
+                            .collect();
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show new network endpoints",
+                            &GitHubCommentGenerator::get_bulleted_list(
+                                &new_network_endpoints,
+                                &Plain,
+                            ),
+                        ));
+                    }
+
+                    checkmark_table.push(vec![
+                        "No likely secrets in added lines",
+                        GitHubCommentGenerator::get_checkmark(stats.new_secrets.is_empty()),
+                    ]);
+                    if !stats.new_secrets.is_empty() {
+                        let new_secrets: Vec<String> = stats
+                            .new_secrets
+                            .iter()
+                            .map(|secret| {
+                                format!("{} in {}", secret.kind, secret.locations.join(", "))
+                            })
+                            .collect();
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show likely secrets",
+                            &GitHubCommentGenerator::get_bulleted_list(&new_secrets, &Plain),
+                        ));
+                    }
+
+                    // custom checks are registered by the caller, shown only if they find any
+                    if !stats.custom_findings.is_empty() {
+                        checkmark_table.push(vec![
+                            "No custom findings",
+                            GitHubCommentGenerator::get_checkmark(false),
+                        ]);
+                        let custom_findings: Vec<String> = stats
+                            .custom_findings
+                            .iter()
+                            .map(|finding| match &finding.file {
+                                Some(file) => format!(
+                                    "{}: {} in {}",
+                                    finding.check,
+                                    finding.message,
+                                    GitHubCommentGenerator::get_text(file, &Code)
+                                ),
+                                None => format!("{}: {}", finding.check, finding.message),
+                            })
+                            .collect();
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show custom findings",
+                            &GitHubCommentGenerator::get_bulleted_list(&custom_findings, &Plain),
+                        ));
+                    }
+
                     checkmark_table.push(vec![
                         "No change in any file with unsafe code",
                         GitHubCommentGenerator::get_checkmark(stats.unsafe_file_changed.is_empty()),
@@ -350,12 +839,54 @@ impl UpdateAnalyzer {
 
             if let Some(crate_source_diff_report) = &report.updated_version.crate_source_diff_report
             {
+                if crate_source_diff_report.repository_mismatch {
+                    checkmark_table.push(vec![
+                        "The repository of the crate contains it",
+                        GitHubCommentGenerator::get_checkmark(false),
+                    ]);
+                    if let Some(detail) = &crate_source_diff_report.repository_mismatch_detail {
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show how the repository does not match the crate",
+                            &GitHubCommentGenerator::get_text(detail, &Plain),
+                        ));
+                    }
+                }
+                if let Some(vcs_info_tag_mismatch) = crate_source_diff_report.vcs_info_tag_mismatch
+                {
+                    checkmark_table.push(vec![
+                        "The release tag points at the commit the crate was published from",
+                        GitHubCommentGenerator::get_checkmark(!vcs_info_tag_mismatch),
+                    ]);
+                }
+                if let Some(license_declaration_mismatch) =
+                    crate_source_diff_report.license_declaration_mismatch
+                {
+                    checkmark_table.push(vec![
+                        "The declared license matches the license files in the git source",
+                        GitHubCommentGenerator::get_checkmark(!license_declaration_mismatch),
+                    ]);
+                }
                 match crate_source_diff_report.is_different {
                     None => {
                         checkmark_table.push(vec![
                             "Depdive failed to compare the crates.io code with its git source",
                             GitHubCommentGenerator::get_emoji(Warning),
                         ]);
+                        if crate_source_diff_report.repository_reachable == Some(false) {
+                            checkmark_table.push(vec![
+                                "The repository of the crate is reachable",
+                                GitHubCommentGenerator::get_checkmark(false),
+                            ]);
+                        }
+                        if !crate_source_diff_report.candidate_tags.is_empty() {
+                            details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                                "Click to show the candidate release tags to manually compare against",
+                                &GitHubCommentGenerator::get_bulleted_list(
+                                    &crate_source_diff_report.candidate_tags,
+                                    &Code,
+                                ),
+                            ));
+                        }
                     }
                     Some(f) => {
                         checkmark_table.push(vec![
@@ -363,6 +894,12 @@ impl UpdateAnalyzer {
                             GitHubCommentGenerator::get_checkmark(!f),
                         ]);
                         if f {
+                            checkmark_table.push(vec![
+                                "No source code differs, e.g., Rust or C",
+                                GitHubCommentGenerator::get_checkmark(
+                                    crate_source_diff_report.is_source_different != Some(true),
+                                ),
+                            ]);
                             let changed_files = crate_source_diff_report
                                 .file_diff_stats
                                 .as_ref()
@@ -380,10 +917,84 @@ impl UpdateAnalyzer {
                                 &GitHubCommentGenerator::get_bulleted_list(&paths, &Code),
                             ));
                         }
+                        // Differing generated code is listed even if left out of the comparison
+                        if let Some(file_diff_stats) = &crate_source_diff_report.file_diff_stats {
+                            if !file_diff_stats.files_generated.is_empty() {
+                                let mut paths: Vec<String> =
+                                    file_diff_stats.files_generated.iter().cloned().collect();
+                                paths.sort();
+                                details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                                    "Click to show the differing files that are generated code",
+                                    &GitHubCommentGenerator::get_bulleted_list(&paths, &Code),
+                                ));
+                            }
+                        }
+                    }
+                }
+                if let Some(gap) = crate_source_diff_report.publish_commit_gap {
+                    details.push_str(&format!(
+                        "Published on crates.io {} days after the release commit\n\n",
+                        (gap.as_secs() / (24 * 60 * 60)).separated_string()
+                    ));
+                }
+                if let Some(compression_stats) = &crate_source_diff_report.compression_stats {
+                    checkmark_table.push(vec![
+                        "The crates.io package has no extreme compression ratio",
+                        GitHubCommentGenerator::get_checkmark(
+                            !compression_stats.suspicious_compression_ratio,
+                        ),
+                    ]);
+                }
+                // Informational only, as tests are often left out of a package on purpose
+                if let Some(tests_present) = crate_source_diff_report.tests_present_in_publish {
+                    details.push_str(match tests_present {
+                        true => "The crates.io package includes tests\n\n",
+                        false => "The crates.io package does not include tests\n\n",
+                    });
+                }
+                if let Some(on_default_branch) = crate_source_diff_report.commit_on_default_branch {
+                    checkmark_table.push(vec![
+                        "The release commit is on the default branch of the git source",
+                        GitHubCommentGenerator::get_checkmark(on_default_branch),
+                    ]);
+                }
+                if crate_source_diff_report.release_commit_analyzed == Some(true) {
+                    checkmark_table.push(vec![
+                        "No published file is excluded by the .gitignore of the git source",
+                        GitHubCommentGenerator::get_checkmark(
+                            crate_source_diff_report.published_ignored_files.is_empty(),
+                        ),
+                    ]);
+                    if !crate_source_diff_report.published_ignored_files.is_empty() {
+                        details.push_str(&GitHubCommentGenerator::get_collapsible_section(
+                            "Click to show the published files excluded by .gitignore",
+                            &GitHubCommentGenerator::get_bulleted_list(
+                                &crate_source_diff_report.published_ignored_files,
+                                &Code,
+                            ),
+                        ));
                     }
                 }
             } else {
-                return Err(anyhow!("no crates source diff report for the new version"));
+                // e.g., in offline mode, where crates.io is not reached
+                checkmark_table.push(vec![
+                    "The crates.io code was not available to compare with its git source",
+                    GitHubCommentGenerator::get_emoji(Warning),
+                ]);
+            }
+
+            // Trusted crates are auto-passed unless there is a hard signal
+            if report.is_auto_passed() {
+                gh.add_text("Trusted by the allowlist, no review needed", &Plain);
+                gh.add_newline(2);
+                continue;
+            }
+            if report.trusted {
+                gh.add_text(
+                    ":warning: Trusted by the allowlist, but requires review",
+                    &Bold,
+                );
+                gh.add_newline(2);
             }
 
             gh.add_html_table(&checkmark_table);
@@ -482,6 +1093,49 @@ impl UpdateAnalyzer {
         commit_a: &str,
         commit_b: &str,
     ) -> Result<Option<String>> {
+        Self::run_update_analyzer_from_repo_commits_with_format(
+            path,
+            commit_a,
+            commit_b,
+            ReportFormat::Markdown,
+            &DepdiveConfig::default(),
+        )
+    }
+
+    /// Get update review report in the given format
+    /// for two commits of a given repo, analyzed with the given config
+    pub fn run_update_analyzer_from_repo_commits_with_format(
+        path: &Path,
+        commit_a: &str,
+        commit_b: &str,
+        format: ReportFormat,
+        config: &DepdiveConfig,
+    ) -> Result<Option<String>> {
+        let (prior_graph, post_graph) =
+            Self::build_graphs_from_repo_commits(path, commit_a, commit_b)?;
+        UpdateAnalyzer::get_report(&prior_graph, &post_graph, format, config)
+    }
+
+    /// Writes the review of each updated dependency to a sink
+    /// for two commits of a given repo, analyzed with the given config
+    pub fn run_update_analyzer_from_repo_commits_to_sink(
+        path: &Path,
+        commit_a: &str,
+        commit_b: &str,
+        sink: &dyn OutputSink,
+        config: &DepdiveConfig,
+    ) -> Result<()> {
+        let (prior_graph, post_graph) =
+            Self::build_graphs_from_repo_commits(path, commit_a, commit_b)?;
+        Self::run_update_analyzer_to_sink(&prior_graph, &post_graph, sink, config)
+    }
+
+    // Builds the graphs at the prior and post commits of a given repo
+    fn build_graphs_from_repo_commits(
+        path: &Path,
+        commit_a: &str,
+        commit_b: &str,
+    ) -> Result<(PackageGraph, PackageGraph)> {
         let repo = Repository::open(&path)?;
         let starter_commit = repo.head()?.peel_to_commit()?;
 
@@ -503,15 +1157,122 @@ impl UpdateAnalyzer {
         let post_graph = MetadataCommand::new().current_dir(path).build_graph()?;
 
         repo.checkout_tree(starter_commit.as_object(), Some(&mut checkout_builder))?;
-        UpdateAnalyzer::get_summary_report(&prior_graph, &post_graph)
+        Ok((prior_graph, post_graph))
     }
 
     /// Get update review report in markdown format
     /// for two paths, presumably checked out at two commits for a given repo
     pub fn run_update_analyzer_from_paths(path_a: &Path, path_b: &Path) -> Result<Option<String>> {
+        Self::run_update_analyzer_from_paths_with_format(
+            path_a,
+            path_b,
+            ReportFormat::Markdown,
+            &DepdiveConfig::default(),
+        )
+    }
+
+    /// Get update review report in the given format
+    /// for two paths, presumably checked out at two commits for a given repo,
+    /// analyzed with the given config
+    pub fn run_update_analyzer_from_paths_with_format(
+        path_a: &Path,
+        path_b: &Path,
+        format: ReportFormat,
+        config: &DepdiveConfig,
+    ) -> Result<Option<String>> {
+        let prior_graph = MetadataCommand::new().current_dir(path_a).build_graph()?;
+        let post_graph = MetadataCommand::new().current_dir(path_b).build_graph()?;
+        UpdateAnalyzer::get_report(&prior_graph, &post_graph, format, config)
+    }
+
+    /// Writes the review of each updated dependency to a sink
+    /// for two paths, presumably checked out at two commits for a given repo,
+    /// analyzed with the given config
+    pub fn run_update_analyzer_from_paths_to_sink(
+        path_a: &Path,
+        path_b: &Path,
+        sink: &dyn OutputSink,
+        config: &DepdiveConfig,
+    ) -> Result<()> {
         let prior_graph = MetadataCommand::new().current_dir(path_a).build_graph()?;
         let post_graph = MetadataCommand::new().current_dir(path_b).build_graph()?;
-        UpdateAnalyzer::get_summary_report(&prior_graph, &post_graph)
+        Self::run_update_analyzer_to_sink(&prior_graph, &post_graph, sink, config)
+    }
+
+    // Leaves out the updates without findings if the config says so
+    fn run_update_analyzer_for_output(
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+        config: &DepdiveConfig,
+    ) -> Result<UpdateReviewReport> {
+        let mut update_analyzer = update::UpdateAnalyzer::new();
+        config.apply_to_update_analyzer(&mut update_analyzer)?;
+        let mut update_review_report = update_analyzer.analyze_updates(prior_graph, post_graph)?;
+        if config.is_only_findings() {
+            update_review_report.retain_findings();
+        }
+        Ok(update_review_report)
+    }
+
+    // Writes the review of each updated dependency to the sink as soon as it completes,
+    // leaving out the updates without findings if the config says so
+    fn run_update_analyzer_to_sink(
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+        sink: &dyn OutputSink,
+        config: &DepdiveConfig,
+    ) -> Result<()> {
+        let mut update_analyzer = update::UpdateAnalyzer::new();
+        config.apply_to_update_analyzer(&mut update_analyzer)?;
+        if config.is_only_findings() {
+            update_analyzer.analyze_updates_to_sink(
+                prior_graph,
+                post_graph,
+                &FindingsOnlySink::new(sink),
+            )?;
+        } else {
+            update_analyzer.analyze_updates_to_sink(prior_graph, post_graph, sink)?;
+        }
+        Ok(())
+    }
+
+    /// Returns None if there is no update to report
+    fn get_report(
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+        format: ReportFormat,
+        config: &DepdiveConfig,
+    ) -> Result<Option<String>> {
+        let update_review_report =
+            Self::run_update_analyzer_for_output(prior_graph, post_graph, config)?;
+        match format {
+            ReportFormat::Markdown => UpdateAnalyzer::get_markdown_report(&update_review_report),
+            ReportFormat::Ndjson => {
+                let ndjson = update_review_report.to_ndjson()?;
+                Ok(if ndjson.is_empty() {
+                    None
+                } else {
+                    Some(ndjson)
+                })
+            }
+            ReportFormat::GitHubAnnotations => {
+                let annotations = update_review_report.to_github_annotations();
+                Ok(if annotations.is_empty() {
+                    None
+                } else {
+                    Some(annotations)
+                })
+            }
+            // the counts are reported even if zero, for a scrape to see the run
+            ReportFormat::Prometheus => Ok(Some(update_review_report.to_prometheus())),
+            ReportFormat::Table => Ok(
+                if update_review_report.dep_update_review_reports.is_empty() {
+                    None
+                } else {
+                    Some(update_review_report.to_table())
+                },
+            ),
+        }
     }
 }
 
@@ -1,6 +1,8 @@
-use anyhow::Result;
-use depdive::{DependencyAnalyzer, DependencyGraphAnalyzer, UpdateAnalyzer};
-use std::path::Path;
+use anyhow::{anyhow, Result};
+use depdive::config::DepdiveConfig;
+use depdive::sink::{FileSink, OutputSink, StdoutSink};
+use depdive::{DependencyAnalyzer, DependencyGraphAnalyzer, ReportFormat, UpdateAnalyzer};
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -16,6 +18,31 @@ enum Command {
     /// Return a review of the dep updates
     /// between a prior and post state
     UpdateReview {
+        #[structopt(long, default_value = "markdown", possible_values = &["markdown", "ndjson", "github-annotations", "prometheus", "table"])]
+        /// Output format of the review
+        format: ReportFormat,
+        #[structopt(long, conflicts_with = "post-url")]
+        /// Write the review in the given format to this file,
+        /// instead of printing it
+        output: Option<PathBuf>,
+        #[structopt(long)]
+        /// Post one json report per updated dependency to this url,
+        /// instead of printing the review (requires the http-sink feature)
+        post_url: Option<String>,
+        #[structopt(long)]
+        /// Leave out the updates without findings,
+        /// while still counting them in the summary
+        only_findings: bool,
+        #[structopt(long, use_delimiter = true)]
+        /// Only analyze these crates, comma separated,
+        /// overriding the crates named in the config
+        only: Vec<String>,
+        #[structopt(long)]
+        /// Config file of the analysis, by default depdive.toml
+        /// at the root of the prior workspace, if present,
+        /// as the update under review must not configure its own review;
+        /// flags given here override the file
+        config: Option<PathBuf>,
         #[structopt(subcommand)]
         cmd: UpdateReviewCommand,
     },
@@ -33,11 +60,19 @@ enum Command {
 enum DepReviewCommand {
     #[structopt(name = "package-metrics")]
     PackageMetrics {
-        /// Path to repo
-        path: String,
+        /// Paths to repos or their Cargo.toml files,
+        /// dependencies across them are analyzed once
+        paths: Vec<String>,
+        #[structopt(long)]
+        /// File listing the paths to analyze, one per line
+        manifest_list: Option<String>,
         #[structopt(long)]
         /// if only direct deps should be analyzed
         only_direct: Option<bool>,
+        #[structopt(long)]
+        /// Config file naming the registry to look the crates up in,
+        /// crates.io if not given
+        config: Option<PathBuf>,
     },
     #[structopt(name = "code-metrics")]
     CodeMetrics {
@@ -70,32 +105,149 @@ enum UpdateReviewCommand {
     },
 }
 
-fn update_analyzer_from_paths(prior: &str, post: &str) -> Result<()> {
-    let report = UpdateAnalyzer::run_update_analyzer_from_paths(Path::new(prior), Path::new(post))?
-        .unwrap_or_default();
-    println!("{}", report);
-    Ok(())
+fn update_analyzer_from_paths(
+    prior: &str,
+    post: &str,
+    format: ReportFormat,
+    output: Option<&Path>,
+    config: &DepdiveConfig,
+) -> Result<()> {
+    let report = UpdateAnalyzer::run_update_analyzer_from_paths_with_format(
+        Path::new(prior),
+        Path::new(post),
+        format,
+        config,
+    )?
+    .unwrap_or_default();
+    write_review(&report, format, output)
 }
 
 fn update_analyzer_from_repo_commits(
     path: &str,
     prior_commit: &str,
     post_commit: &str,
+    format: ReportFormat,
+    output: Option<&Path>,
+    config: &DepdiveConfig,
 ) -> Result<()> {
-    let report = UpdateAnalyzer::run_update_analyzer_from_repo_commits(
+    let report = UpdateAnalyzer::run_update_analyzer_from_repo_commits_with_format(
         Path::new(path),
         prior_commit,
         post_commit,
+        format,
+        config,
     )?
     .unwrap_or_default();
-    println!("{}", report);
+    write_review(&report, format, output)
+}
+
+// Prints the formatted review, or writes it to the output file if given
+fn write_review(report: &str, format: ReportFormat, output: Option<&Path>) -> Result<()> {
+    let mut review = report.to_string();
+    if format == ReportFormat::Markdown {
+        review.push('\n');
+    }
+    match output {
+        Some(output) => std::fs::write(output, review)?,
+        None => print!("{}", review),
+    }
     Ok(())
 }
 
-fn get_package_metrics_for_deps_in_json(path: &str, only_direct: Option<bool>) -> Result<()> {
-    let report = DependencyAnalyzer::get_dep_package_metrics_in_json_from_path(
-        Path::new(path),
+// Returns the sink to deliver the reports to, if any is given,
+// or stdout or the output file for ndjson, in which case the review is not written
+// as a whole but each report as soon as it completes
+// Other formats are only rendered for the whole review, therefore take no sink
+fn get_output_sink(
+    output: Option<&Path>,
+    post_url: Option<&str>,
+    format: ReportFormat,
+) -> Result<Option<Box<dyn OutputSink>>> {
+    if let Some(output) = output {
+        if format != ReportFormat::Ndjson {
+            return Ok(None);
+        }
+        return Ok(Some(Box::new(FileSink::new(output)?)));
+    }
+    match post_url {
+        #[cfg(feature = "http-sink")]
+        Some(url) => Ok(Some(Box::new(depdive::sink::HttpSink::new(url)))),
+        #[cfg(not(feature = "http-sink"))]
+        Some(_url) => Err(anyhow!("depdive is built without the http-sink feature")),
+        None if format == ReportFormat::Ndjson => Ok(Some(Box::new(StdoutSink))),
+        None => Ok(None),
+    }
+}
+
+// Loads the given config file, or else the one at the root of the prior workspace, if any,
+// with the flags given on the command line taking precedence
+fn get_config(
+    config_path: Option<&Path>,
+    cmd: &UpdateReviewCommand,
+    only_findings: bool,
+    only: &[String],
+) -> Result<DepdiveConfig> {
+    let mut config = match (config_path, cmd) {
+        (Some(config_path), _) => DepdiveConfig::from_file(config_path)?,
+        (None, UpdateReviewCommand::Paths { prior, .. }) => {
+            DepdiveConfig::from_workspace_root(Path::new(prior))?.unwrap_or_default()
+        }
+        (None, UpdateReviewCommand::Commits { path, prior, .. }) => {
+            DepdiveConfig::from_repo_commit(Path::new(path), prior)?.unwrap_or_default()
+        }
+    };
+    if only_findings {
+        config.only_findings = Some(true);
+    }
+    if !only.is_empty() {
+        config.only_crates = Some(only.to_vec());
+    }
+    Ok(config)
+}
+
+// Reads the paths listed in a file, one per line,
+// skipping empty lines and # comments
+// Relative paths are resolved against the directory of the file
+fn read_manifest_list(manifest_list: &str) -> Result<Vec<PathBuf>> {
+    let manifest_list = Path::new(manifest_list);
+    let base_dir = manifest_list.parent().unwrap_or_else(|| Path::new(""));
+    let content = std::fs::read_to_string(manifest_list)?;
+    Ok(content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base_dir.join(line))
+        .collect())
+}
+
+// Returns the paths given on the command line followed by the listed ones
+fn get_manifest_paths(paths: &[String], manifest_list: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    if let Some(manifest_list) = manifest_list {
+        paths.extend(read_manifest_list(manifest_list)?);
+    }
+    if paths.is_empty() {
+        return Err(anyhow!("no path given to analyze"));
+    }
+    Ok(paths)
+}
+
+fn get_package_metrics_for_deps_in_json(
+    paths: &[String],
+    manifest_list: Option<&str>,
+    only_direct: Option<bool>,
+    config_path: Option<&Path>,
+) -> Result<()> {
+    let paths = get_manifest_paths(paths, manifest_list)?;
+    let paths: Vec<&Path> = paths.iter().map(|path| path.as_path()).collect();
+    let config = match config_path {
+        Some(config_path) => DepdiveConfig::from_file(config_path)?,
+        None => DepdiveConfig::default(),
+    };
+    let report = DependencyAnalyzer::get_dep_package_metrics_in_json_from_paths(
+        &paths,
         only_direct.unwrap_or(false),
+        &config.registry.get_registry()?,
     )?;
     println!("{}", report);
     Ok(())
@@ -114,19 +266,252 @@ fn main() -> Result<()> {
     let args = Args::from_iter(std::env::args());
 
     match args.cmd {
-        Command::UpdateReview { cmd } => match cmd {
-            UpdateReviewCommand::Paths { prior, post } => update_analyzer_from_paths(&prior, &post),
-            UpdateReviewCommand::Commits { path, prior, post } => {
-                update_analyzer_from_repo_commits(&path, &prior, &post)
+        Command::UpdateReview {
+            format,
+            output,
+            post_url,
+            only_findings,
+            only,
+            config,
+            cmd,
+        } => {
+            let config = get_config(config.as_deref(), &cmd, only_findings, &only)?;
+            match (
+                get_output_sink(output.as_deref(), post_url.as_deref(), format)?,
+                cmd,
+            ) {
+                (Some(sink), UpdateReviewCommand::Paths { prior, post }) => {
+                    UpdateAnalyzer::run_update_analyzer_from_paths_to_sink(
+                        Path::new(&prior),
+                        Path::new(&post),
+                        sink.as_ref(),
+                        &config,
+                    )
+                }
+                (Some(sink), UpdateReviewCommand::Commits { path, prior, post }) => {
+                    UpdateAnalyzer::run_update_analyzer_from_repo_commits_to_sink(
+                        Path::new(&path),
+                        &prior,
+                        &post,
+                        sink.as_ref(),
+                        &config,
+                    )
+                }
+                (None, UpdateReviewCommand::Paths { prior, post }) => {
+                    update_analyzer_from_paths(&prior, &post, format, output.as_deref(), &config)
+                }
+                (None, UpdateReviewCommand::Commits { path, prior, post }) => {
+                    update_analyzer_from_repo_commits(
+                        &path,
+                        &prior,
+                        &post,
+                        format,
+                        output.as_deref(),
+                        &config,
+                    )
+                }
             }
-        },
+        }
         Command::DepReview { cmd } => match cmd {
-            DepReviewCommand::PackageMetrics { path, only_direct } => {
-                get_package_metrics_for_deps_in_json(&path, only_direct)
-            }
+            DepReviewCommand::PackageMetrics {
+                paths,
+                manifest_list,
+                only_direct,
+                config,
+            } => get_package_metrics_for_deps_in_json(
+                &paths,
+                manifest_list.as_deref(),
+                only_direct,
+                config.as_deref(),
+            ),
             DepReviewCommand::CodeMetrics { path, only_direct } => {
                 get_code_metrics_for_deps_in_json(&path, only_direct)
             }
         },
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_main_output_sink() {
+        let dir = tempdir().unwrap();
+        let output = dir.path().join("reports.ndjson");
+        let get_output_sink = |output: Option<&Path>, format: ReportFormat| {
+            get_output_sink(output, None, format).unwrap().is_some()
+        };
+
+        // ndjson is streamed to stdout unless another sink is given
+        assert!(get_output_sink(None, ReportFormat::Ndjson));
+        assert!(!get_output_sink(None, ReportFormat::Markdown));
+        assert!(get_output_sink(Some(&output), ReportFormat::Ndjson));
+        assert!(output.exists());
+
+        // other formats are written to the output file as a whole
+        let output = dir.path().join("review.md");
+        assert!(!get_output_sink(Some(&output), ReportFormat::Markdown));
+        write_review("# review", ReportFormat::Markdown, Some(&output)).unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "# review\n");
+    }
+
+    #[test]
+    fn test_main_only_crates() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("depdive.toml");
+        std::fs::write(&config_path, "only_crates = [\"libc\"]\n").unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        let get_only_crates = |args: &[&str]| {
+            let args = Args::from_iter(
+                ["depdive", "update-review", "--config", config_path]
+                    .iter()
+                    .chain(args)
+                    .chain(&["paths", "prior", "post"]),
+            );
+            match args.cmd {
+                Command::UpdateReview {
+                    only_findings,
+                    only,
+                    config,
+                    cmd,
+                    ..
+                } => {
+                    get_config(config.as_deref(), &cmd, only_findings, &only)
+                        .unwrap()
+                        .only_crates
+                }
+                _ => panic!("not parsed as update review"),
+            }
+        };
+
+        assert_eq!(get_only_crates(&[]), Some(vec!["libc".to_string()]));
+        assert_eq!(
+            get_only_crates(&["--only", "guppy,cargo_metadata"]),
+            Some(vec!["guppy".to_string(), "cargo_metadata".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_main_config_from_prior_state() {
+        let dir = tempdir().unwrap();
+        let prior = dir.path().join("prior");
+        let post = dir.path().join("post");
+        std::fs::create_dir_all(&prior).unwrap();
+        std::fs::create_dir_all(&post).unwrap();
+        // The update under review ships a config excluding itself
+        let post_config = "exclude_crates = [\"*\"]\n";
+        std::fs::write(post.join("depdive.toml"), post_config).unwrap();
+
+        let cmd = UpdateReviewCommand::Paths {
+            prior: prior.to_str().unwrap().to_string(),
+            post: post.to_str().unwrap().to_string(),
+        };
+        assert_eq!(
+            get_config(None, &cmd, false, &[]).unwrap(),
+            DepdiveConfig::default()
+        );
+        std::fs::write(prior.join("depdive.toml"), "only_findings = true\n").unwrap();
+        assert!(get_config(None, &cmd, false, &[])
+            .unwrap()
+            .is_only_findings());
+
+        // Likewise, the config of the prior commit is read, not the checked out one
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("depdive", "depdive@example.com").unwrap();
+        let commit = |parents: &[&git2::Commit]| {
+            let mut index = repo.index().unwrap();
+            index
+                .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+                .unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            repo.commit(None, &signature, &signature, "update", &tree, parents)
+                .unwrap()
+        };
+        std::fs::remove_dir_all(&prior).unwrap();
+        std::fs::remove_dir_all(&post).unwrap();
+        let prior_commit = commit(&[]);
+        std::fs::write(dir.path().join("depdive.toml"), post_config).unwrap();
+        let post_commit = commit(&[&repo.find_commit(prior_commit).unwrap()]);
+
+        let cmd = UpdateReviewCommand::Commits {
+            path: dir.path().to_str().unwrap().to_string(),
+            prior: prior_commit.to_string(),
+            post: post_commit.to_string(),
+        };
+        assert_eq!(
+            get_config(None, &cmd, false, &[]).unwrap(),
+            DepdiveConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_main_manifest_list() {
+        let dir = tempdir().unwrap();
+        let manifest_list = dir.path().join("manifests.txt");
+        std::fs::write(
+            &manifest_list,
+            "# independent workspaces\nservice/Cargo.toml\n\n  tools  \n",
+        )
+        .unwrap();
+        let manifest_list = manifest_list.to_str().unwrap();
+
+        let args = Args::from_iter(&[
+            "depdive",
+            "dep-review",
+            "package-metrics",
+            "resources/test/valid_dep",
+            "--manifest-list",
+            manifest_list,
+        ]);
+        let paths = match args.cmd {
+            Command::DepReview {
+                cmd:
+                    DepReviewCommand::PackageMetrics {
+                        paths,
+                        manifest_list,
+                        ..
+                    },
+            } => get_manifest_paths(&paths, manifest_list.as_deref()).unwrap(),
+            _ => panic!("not parsed as package metrics"),
+        };
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("resources/test/valid_dep"),
+                dir.path().join("service/Cargo.toml"),
+                dir.path().join("tools"),
+            ]
+        );
+
+        // The list alone is enough
+        let args = Args::from_iter(&[
+            "depdive",
+            "dep-review",
+            "package-metrics",
+            "--manifest-list",
+            manifest_list,
+        ]);
+        match args.cmd {
+            Command::DepReview {
+                cmd:
+                    DepReviewCommand::PackageMetrics {
+                        paths,
+                        manifest_list,
+                        ..
+                    },
+            } => {
+                assert_eq!(
+                    get_manifest_paths(&paths, manifest_list.as_deref())
+                        .unwrap()
+                        .len(),
+                    2
+                );
+                assert!(get_manifest_paths(&paths, None).is_err());
+            }
+            _ => panic!("not parsed as package metrics"),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! This module abstracts fetching the OSSF Scorecard reports of repositories,
+//! either from a downloaded scorecard dataset or the scorecard api
+
+use anyhow::{anyhow, Result};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::diff::trim_remote_url;
+
+const OSSF_SCORECARD_API_BASE_URL: &str = "https://api.securityscorecards.dev";
+
+/// A scorecard report of a repository as published by OSSF
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OSSFReport {
+    pub date: String,
+    pub repo: OSSFRepo,
+    pub score: Option<f64>, // aggregate score, absent in older datasets
+    pub checks: Vec<OSSFCheck>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OSSFRepo {
+    pub name: String, // e.g., github.com/owner/repo
+    pub commit: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OSSFCheck {
+    pub name: String,
+    pub score: Option<i64>, // -1 when the check is inconclusive
+    pub reason: Option<String>,
+}
+
+/// The scorecard report of a package, resolved through its repository
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PackageOSSFReport {
+    pub name: String,
+    pub version: String,
+    pub repository: Option<String>, // None if the package declares no repository
+    pub ossf_report: Option<OSSFReport>, // None if the repository has not been scored
+}
+
+// Buckets of the aggregate score histogram, i.e., [0, 1), [1, 2), ..., [9, 10]
+const SCORE_HISTOGRAM_BUCKETS: usize = 10;
+
+/// How much of a dependency set the scorecard reports cover,
+/// with the aggregate scores of the covered packages bucketed by integer score
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct OSSFCoverage {
+    pub covered: usize,
+    pub uncovered_no_repo: usize,
+    pub uncovered_not_in_dataset: usize,
+    // counts of aggregate scores per bucket, leaving out reports with no aggregate score
+    pub score_histogram: Vec<usize>,
+}
+
+impl OSSFCoverage {
+    pub fn from_reports(reports: &[PackageOSSFReport]) -> Self {
+        let mut coverage = Self {
+            score_histogram: vec![0; SCORE_HISTOGRAM_BUCKETS],
+            ..Default::default()
+        };
+        for report in reports {
+            match (&report.repository, &report.ossf_report) {
+                (None, _) => coverage.uncovered_no_repo += 1,
+                (Some(_), None) => coverage.uncovered_not_in_dataset += 1,
+                (Some(_), Some(ossf_report)) => {
+                    coverage.covered += 1;
+                    if let Some(score) = ossf_report.score {
+                        // a perfect 10 falls in the last bucket
+                        let bucket = (score.max(0.0) as usize).min(SCORE_HISTOGRAM_BUCKETS - 1);
+                        coverage.score_histogram[bucket] += 1;
+                    }
+                }
+            }
+        }
+        coverage
+    }
+}
+
+pub struct OSSFClient {
+    http_client: reqwest::blocking::Client,
+    api_base_url: String,
+    // a scorecard dataset as json lines, one report per line
+    dataset_path: Option<PathBuf>,
+}
+
+impl OSSFClient {
+    pub fn new() -> Result<Self> {
+        Self::with_api(OSSF_SCORECARD_API_BASE_URL)
+    }
+
+    /// Creates a client against a scorecard api at the given base url
+    pub fn with_api(api_base_url: &str) -> Result<Self> {
+        Ok(Self {
+            http_client: reqwest::blocking::Client::builder()
+                .user_agent("diem/whackadep")
+                .build()?,
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+            dataset_path: None,
+        })
+    }
+
+    /// Looks up reports in a local scorecard dataset instead of the api
+    pub fn set_dataset_path(&mut self, path: &Path) -> &mut Self {
+        self.dataset_path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Returns the scorecard report of a single repository,
+    /// or None if the repository has not been scored
+    pub fn get_ossf_report_for_repo(&self, repo_url: &str) -> Result<Option<OSSFReport>> {
+        let repo_name = Self::get_ossf_repo_name(repo_url)?;
+        match &self.dataset_path {
+            Some(dataset_path) => Self::get_ossf_report_from_dataset(dataset_path, &repo_name),
+            None => self.get_ossf_report_from_api(&repo_name),
+        }
+    }
+
+    /// Returns the scorecard report of a package through its repository, if any
+    pub fn get_ossf_report_for_package(
+        &self,
+        name: &str,
+        version: &str,
+        repository: Option<&str>,
+    ) -> Result<PackageOSSFReport> {
+        let ossf_report = match repository {
+            Some(repository) => self.get_ossf_report_for_repo(repository)?,
+            None => None,
+        };
+        Ok(PackageOSSFReport {
+            name: name.to_string(),
+            version: version.to_string(),
+            repository: repository.map(|repository| repository.to_string()),
+            ossf_report,
+        })
+    }
+
+    // Scorecard refers to repositories as "host/owner/repo"
+    fn get_ossf_repo_name(repo_url: &str) -> Result<String> {
+        let url = trim_remote_url(repo_url)?;
+        Ok(url.trim_start_matches("https://").to_string())
+    }
+
+    // Reads through the dataset without loading it whole in memory,
+    // as the full dataset covers over a million repositories
+    fn get_ossf_report_from_dataset(
+        dataset_path: &Path,
+        repo_name: &str,
+    ) -> Result<Option<OSSFReport>> {
+        let reader = BufReader::new(File::open(dataset_path)?);
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let report: OSSFReport = serde_json::from_str(&line).map_err(|e| {
+                anyhow!(
+                    "invalid scorecard report in {}: {}",
+                    dataset_path.display(),
+                    e
+                )
+            })?;
+            if report.repo.name.eq_ignore_ascii_case(repo_name) {
+                return Ok(Some(report));
+            }
+        }
+        Ok(None)
+    }
+
+    fn get_ossf_report_from_api(&self, repo_name: &str) -> Result<Option<OSSFReport>> {
+        let api_endpoint = format!("{}/projects/{}", self.api_base_url, repo_name);
+        let response = self.http_client.get(&api_endpoint).send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ossf_report_for_repo_from_dataset() {
+        let mut ossf_client = OSSFClient::new().unwrap();
+        ossf_client.set_dataset_path(Path::new("resources/test/ossf_scorecard_dataset.json"));
+
+        let report = ossf_client
+            .get_ossf_report_for_repo("https://github.com/rust-lang/libc.git")
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.repo.name, "github.com/rust-lang/libc");
+        assert_eq!(report.score, Some(6.4));
+        assert_eq!(report.checks.len(), 2);
+        assert_eq!(report.checks[0].name, "Code-Review");
+        assert_eq!(report.checks[0].score, Some(8));
+
+        assert!(ossf_client
+            .get_ossf_report_for_repo("https://github.com/nasifimtiazohi/unscored")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_ossf_coverage() {
+        let mut ossf_client = OSSFClient::new().unwrap();
+        ossf_client.set_dataset_path(Path::new("resources/test/ossf_scorecard_dataset.json"));
+
+        let reports: Vec<PackageOSSFReport> = [
+            ("tokio", Some("https://github.com/tokio-rs/tokio")),
+            ("libc", Some("https://github.com/rust-lang/libc")),
+            (
+                "unscored",
+                Some("https://github.com/nasifimtiazohi/unscored"),
+            ),
+            ("no-repo", None),
+        ]
+        .iter()
+        .map(|(name, repository)| {
+            ossf_client
+                .get_ossf_report_for_package(name, "0.1.0", *repository)
+                .unwrap()
+        })
+        .collect();
+
+        let coverage = OSSFCoverage::from_reports(&reports);
+        assert_eq!(coverage.covered, 2);
+        assert_eq!(coverage.uncovered_no_repo, 1);
+        assert_eq!(coverage.uncovered_not_in_dataset, 1);
+        assert_eq!(coverage.score_histogram, vec![0, 0, 0, 0, 0, 0, 1, 1, 0, 0]);
+    }
+}
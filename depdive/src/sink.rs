@@ -0,0 +1,176 @@
+//! This module abstracts the delivery of update review reports,
+//! e.g., to stdout, a file, or an http endpoint,
+//! so that analysis is decoupled from where the reports go
+
+use anyhow::Result;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use crate::update::DepUpdateReviewReport;
+
+/// A destination for the review report of each updated dependency
+pub trait OutputSink {
+    fn write_report(&self, report: &DepUpdateReviewReport) -> Result<()>;
+}
+
+/// Prints each report as a line of json
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn write_report(&self, report: &DepUpdateReviewReport) -> Result<()> {
+        println!("{}", serde_json::to_string(report)?);
+        Ok(())
+    }
+}
+
+/// Writes each report as a line of json to a file,
+/// created, or truncated if exists, along with the sink
+pub struct FileSink {
+    file: File,
+}
+
+impl FileSink {
+    pub fn new(path: &Path) -> Result<Self> {
+        Ok(Self {
+            file: File::create(path)?,
+        })
+    }
+}
+
+impl OutputSink for FileSink {
+    fn write_report(&self, report: &DepUpdateReviewReport) -> Result<()> {
+        let mut file = &self.file;
+        writeln!(file, "{}", serde_json::to_string(report)?)?;
+        Ok(())
+    }
+}
+
+/// Passes on to another sink only the reports with findings
+/// (see `DepUpdateReviewReport::has_findings`)
+pub struct FindingsOnlySink<'a> {
+    sink: &'a dyn OutputSink,
+}
+
+impl<'a> FindingsOnlySink<'a> {
+    pub fn new(sink: &'a dyn OutputSink) -> Self {
+        Self { sink }
+    }
+}
+
+impl OutputSink for FindingsOnlySink<'_> {
+    fn write_report(&self, report: &DepUpdateReviewReport) -> Result<()> {
+        if report.has_findings() {
+            self.sink.write_report(report)?;
+        }
+        Ok(())
+    }
+}
+
+/// Posts each report as json to an http endpoint
+#[cfg(feature = "http-sink")]
+pub struct HttpSink {
+    client: reqwest::blocking::Client,
+    url: String,
+}
+
+#[cfg(feature = "http-sink")]
+impl HttpSink {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "http-sink")]
+impl OutputSink for HttpSink {
+    fn write_report(&self, report: &DepUpdateReviewReport) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(report)
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::update::test::get_test_report;
+    use crate::update::UpdateReviewReport;
+    use semver::Version;
+    use std::cell::RefCell;
+    use tempfile::tempdir;
+
+    // Captures the reports in memory for assertion
+    #[derive(Default)]
+    pub(crate) struct MemorySink {
+        pub(crate) reports: RefCell<Vec<DepUpdateReviewReport>>,
+    }
+
+    impl OutputSink for MemorySink {
+        fn write_report(&self, report: &DepUpdateReviewReport) -> Result<()> {
+            self.reports.borrow_mut().push(report.clone());
+            Ok(())
+        }
+    }
+
+    // The source of tokio matches its repository, and nothing else is found,
+    // whereas a file is injected into the source of libc
+    fn get_test_update_review_report() -> UpdateReviewReport {
+        UpdateReviewReport {
+            dep_update_review_reports: vec![
+                get_test_report("tokio", None),
+                get_test_report("libc", Some("src/injected.rs")),
+            ],
+            version_conflicts: Vec::new(),
+            introduced_dependencies: Vec::new(),
+            omitted_clean_crates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_sink_memory() {
+        let sink = MemorySink::default();
+        get_test_update_review_report()
+            .write_to_sink(&sink)
+            .unwrap();
+
+        let reports = sink.reports.borrow();
+        let names: Vec<&str> = reports.iter().map(|report| report.name.as_str()).collect();
+        assert_eq!(names, vec!["libc", "tokio"]);
+        assert_eq!(
+            reports[0].updated_version.version,
+            Version::parse("0.1.1").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sink_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("reports.ndjson");
+        let sink = FileSink::new(&path).unwrap();
+        let report = get_test_update_review_report();
+        report.write_to_sink(&sink).unwrap();
+
+        // same as the ndjson output
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            report.to_ndjson().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sink_findings_only() {
+        let sink = MemorySink::default();
+        get_test_update_review_report()
+            .write_to_sink(&FindingsOnlySink::new(&sink))
+            .unwrap();
+        let reports = sink.reports.borrow();
+        let names: Vec<&str> = reports.iter().map(|report| report.name.as_str()).collect();
+        assert_eq!(names, vec!["libc"]);
+    }
+}
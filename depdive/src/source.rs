@@ -0,0 +1,69 @@
+//! This module abstracts where the source of a crate version is read from
+//! when crates.io is unreachable, e.g., in air-gapped or offline runs
+
+use anyhow::{anyhow, Result};
+use guppy::graph::PackageGraph;
+use semver::Version;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Provides the source of a crate version without network access
+pub trait SourceProvider {
+    /// Returns the directory of the crate version containing its Cargo.toml
+    fn get_source_dir(&self, name: &str, version: &Version) -> Result<PathBuf>;
+}
+
+/// Provides the sources already on disk for the packages of guppy graphs,
+/// e.g., unpacked by cargo in the local registry cache
+#[derive(Default)]
+pub struct LocalSourceProvider {
+    source_dirs: HashMap<(String, Version), PathBuf>,
+}
+
+impl LocalSourceProvider {
+    pub fn new(graphs: &[&PackageGraph]) -> Self {
+        let mut source_dirs: HashMap<(String, Version), PathBuf> = HashMap::new();
+        for graph in graphs {
+            for package in graph.packages() {
+                if let Some(source_dir) = package.manifest_path().parent() {
+                    source_dirs.insert(
+                        (package.name().to_string(), package.version().clone()),
+                        source_dir.to_path_buf().into(),
+                    );
+                }
+            }
+        }
+        Self { source_dirs }
+    }
+}
+
+impl SourceProvider for LocalSourceProvider {
+    fn get_source_dir(&self, name: &str, version: &Version) -> Result<PathBuf> {
+        self.source_dirs
+            .get(&(name.to_string(), version.clone()))
+            .cloned()
+            .ok_or_else(|| anyhow!("no local source found for {} {}", name, version))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use guppy::MetadataCommand;
+
+    #[test]
+    fn test_source_local_provider() {
+        let graph = MetadataCommand::new().build_graph().unwrap();
+        let source_provider = LocalSourceProvider::new(&[&graph]);
+
+        let package = graph.packages().find(|p| p.name() == "depdive").unwrap();
+        let source_dir = source_provider
+            .get_source_dir("depdive", package.version())
+            .unwrap();
+        assert!(source_dir.join("Cargo.toml").exists());
+
+        assert!(source_provider
+            .get_source_dir("depdive", &Version::parse("0.0.0").unwrap())
+            .is_err());
+    }
+}
@@ -10,7 +10,8 @@ use guppy::{
     DependencyKind,
 };
 use indoc::indoc;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::{copy, create_dir_all, read_to_string, File, OpenOptions};
 use std::hash::{Hash, Hasher};
 use std::io::Write;
@@ -222,6 +223,40 @@ pub enum CargoTomlType {
     VirtualManifest,
 }
 
+/// Number of dependencies declared in a Cargo.toml per kind,
+/// counted once per name across targets
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct DependencyCounts {
+    pub normal: u64,
+    pub build: u64,
+    pub dev: u64,
+    pub optional: u64, // among the normal and build dependencies
+}
+
+/// The docs.rs build configuration of a package,
+/// declared under `[package.metadata.docs.rs]`
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct DocsrsMetadata {
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub features: Vec<String>,
+    pub targets: Vec<String>, // empty for the default docs.rs targets
+    pub default_target: Option<String>,
+}
+
+/// A dependency as declared in a Cargo.toml,
+/// where a plain version string and a table with only a version compare equal
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeclaredDependency {
+    pub target: Option<String>, // e.g., cfg(unix) for a target specific dependency
+    pub kind: String,           // dependencies, build-dependencies, or dev-dependencies
+    pub name: String,
+    pub version: Option<String>,
+    pub features: BTreeSet<String>,
+    pub optional: bool,
+    pub default_features: bool,
+}
+
 /// Holds path to a Cargo.toml file
 /// and returns value for various fields
 /// and checks if it is a package toml or a virtual manifest
@@ -242,6 +277,16 @@ impl CargoTomlParser {
         })
     }
 
+    /// Parses Cargo.toml content that is not on the file system,
+    /// e.g., read from a git tree,
+    /// where path is only used to refer to the file in errors
+    pub fn from_content(content: &str, path: &str) -> Result<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            toml: toml::from_str(content)?,
+        })
+    }
+
     pub fn get_package_name(&self) -> Result<String> {
         Ok(self
             .toml
@@ -266,6 +311,277 @@ impl CargoTomlParser {
             .to_string())
     }
 
+    /// Returns the minimum supported Rust version, if declared
+    pub fn get_rust_version(&self) -> Result<Option<String>> {
+        Ok(self
+            .toml
+            .get("package")
+            .ok_or_else(|| anyhow!("no package section found for {}", self.path))?
+            .get("rust-version")
+            .and_then(|rust_version| rust_version.as_str())
+            .map(|rust_version| rust_version.to_string()))
+    }
+
+    /// Returns the repository url, if declared
+    pub fn get_package_repository(&self) -> Result<Option<String>> {
+        Ok(self
+            .toml
+            .get("package")
+            .ok_or_else(|| anyhow!("no package section found for {}", self.path))?
+            .get("repository")
+            .and_then(|repository| repository.as_str())
+            .map(|repository| repository.to_string()))
+    }
+
+    /// Returns the SPDX license expression, if declared
+    pub fn get_package_license(&self) -> Result<Option<String>> {
+        Ok(self
+            .toml
+            .get("package")
+            .ok_or_else(|| anyhow!("no package section found for {}", self.path))?
+            .get("license")
+            .and_then(|license| license.as_str())
+            .map(|license| license.to_string()))
+    }
+
+    /// Returns the `include` patterns for the files to publish, if any
+    pub fn get_package_include(&self) -> Result<Vec<String>> {
+        self.get_package_string_array("include")
+    }
+
+    /// Returns the `exclude` patterns for the files not to publish, if any
+    pub fn get_package_exclude(&self) -> Result<Vec<String>> {
+        self.get_package_string_array("exclude")
+    }
+
+    /// Returns the docs.rs build configuration, the default one if not declared
+    pub fn get_docsrs_metadata(&self) -> Result<DocsrsMetadata> {
+        let metadata = match self
+            .toml
+            .get("package")
+            .ok_or_else(|| anyhow!("no package section found for {}", self.path))?
+            .get("metadata")
+            .and_then(|metadata| metadata.get("docs"))
+            .and_then(|docs| docs.get("rs"))
+        {
+            Some(metadata) => metadata,
+            None => return Ok(DocsrsMetadata::default()),
+        };
+
+        let get_bool = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false)
+        };
+        let get_strings = |key: &str| -> Vec<String> {
+            metadata
+                .get(key)
+                .and_then(|values| values.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|value| value.as_str())
+                .map(|value| value.to_string())
+                .collect()
+        };
+        Ok(DocsrsMetadata {
+            all_features: get_bool("all-features"),
+            no_default_features: get_bool("no-default-features"),
+            features: get_strings("features"),
+            targets: get_strings("targets"),
+            default_target: metadata
+                .get("default-target")
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string()),
+        })
+    }
+
+    fn get_package_string_array(&self, key: &str) -> Result<Vec<String>> {
+        let values = match self
+            .toml
+            .get("package")
+            .ok_or_else(|| anyhow!("no package section found for {}", self.path))?
+            .get(key)
+        {
+            Some(values) => values
+                .as_array()
+                .ok_or_else(|| anyhow!("package {} is not an array for {}", key, self.path))?,
+            None => return Ok(Vec::new()),
+        };
+
+        values
+            .iter()
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(|value| value.to_string())
+                    .ok_or_else(|| {
+                        anyhow!("package {} has a non-string value for {}", key, self.path)
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns each feature mapped to the features and optional dependencies it enables,
+    /// including the implicit feature of an optional dependency,
+    /// unless the dependency is only referred to with the `dep:` prefix
+    pub fn get_feature_graph(&self) -> Result<HashMap<String, Vec<String>>> {
+        let mut feature_graph: HashMap<String, Vec<String>> = HashMap::new();
+        if let Some(features) = self.toml.get("features") {
+            let features = features
+                .as_table()
+                .ok_or_else(|| anyhow!("features is not a table for {}", self.path))?;
+            for (feature, enables) in features {
+                let enables = enables
+                    .as_array()
+                    .ok_or_else(|| {
+                        anyhow!("feature {} is not an array for {}", feature, self.path)
+                    })?
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map(|value| value.to_string())
+                            .ok_or_else(|| {
+                                anyhow!(
+                                    "feature {} has a non-string value for {}",
+                                    feature,
+                                    self.path
+                                )
+                            })
+                    })
+                    .collect::<Result<Vec<String>>>()?;
+                feature_graph.insert(feature.clone(), enables);
+            }
+        }
+
+        let dep_prefixed: HashSet<String> = feature_graph
+            .values()
+            .flatten()
+            .filter_map(|value| value.strip_prefix("dep:"))
+            .map(|dep| dep.to_string())
+            .collect();
+        for dep in self.get_optional_dependencies() {
+            if !dep_prefixed.contains(&dep) && !feature_graph.contains_key(&dep) {
+                let enables = vec![format!("dep:{}", dep)];
+                feature_graph.insert(dep, enables);
+            }
+        }
+
+        Ok(feature_graph)
+    }
+
+    /// Returns the names of the normal dependencies,
+    /// including the target specific ones
+    pub fn get_dependencies(&self) -> HashSet<String> {
+        self.get_dependency_names(&["dependencies"], false)
+    }
+
+    /// Returns the names of the build dependencies,
+    /// including the target specific ones
+    pub fn get_build_dependencies(&self) -> HashSet<String> {
+        self.get_dependency_names(&["build-dependencies"], false)
+    }
+
+    /// Returns the names of the dev dependencies,
+    /// including the target specific ones
+    pub fn get_dev_dependencies(&self) -> HashSet<String> {
+        self.get_dependency_names(&["dev-dependencies"], false)
+    }
+
+    /// Returns the names of the optional dependencies,
+    /// including the target specific ones
+    pub fn get_optional_dependencies(&self) -> HashSet<String> {
+        self.get_dependency_names(&["dependencies", "build-dependencies"], true)
+    }
+
+    /// Returns the number of dependencies declared per kind
+    pub fn get_dependency_counts(&self) -> DependencyCounts {
+        DependencyCounts {
+            normal: self.get_dependencies().len() as u64,
+            build: self.get_build_dependencies().len() as u64,
+            dev: self.get_dev_dependencies().len() as u64,
+            optional: self.get_optional_dependencies().len() as u64,
+        }
+    }
+
+    /// Returns all the declared dependencies of all kinds and targets
+    /// with their versions and features
+    pub fn get_declared_dependencies(&self) -> BTreeSet<DeclaredDependency> {
+        let mut tables: Vec<(Option<String>, &toml::Value)> = vec![(None, &self.toml)];
+        if let Some(targets) = self.toml.get("target").and_then(|t| t.as_table()) {
+            tables.extend(
+                targets
+                    .iter()
+                    .map(|(target, table)| (Some(target.clone()), table)),
+            );
+        }
+
+        let mut declared_dependencies = BTreeSet::new();
+        for (target, table) in tables {
+            for kind in &["dependencies", "build-dependencies", "dev-dependencies"] {
+                let dependencies = match table.get(kind).and_then(|d| d.as_table()) {
+                    Some(dependencies) => dependencies,
+                    None => continue,
+                };
+                for (name, dependency) in dependencies {
+                    let get_bool = |keys: &[&str], default: bool| {
+                        keys.iter()
+                            .find_map(|key| dependency.get(key).and_then(|v| v.as_bool()))
+                            .unwrap_or(default)
+                    };
+                    declared_dependencies.insert(DeclaredDependency {
+                        target: target.clone(),
+                        kind: kind.to_string(),
+                        name: name.clone(),
+                        version: dependency
+                            .as_str()
+                            .or_else(|| dependency.get("version").and_then(|v| v.as_str()))
+                            .map(|version| version.to_string()),
+                        features: dependency
+                            .get("features")
+                            .and_then(|features| features.as_array())
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|feature| feature.as_str())
+                            .map(|feature| feature.to_string())
+                            .collect(),
+                        optional: get_bool(&["optional"], false),
+                        default_features: get_bool(&["default-features", "default_features"], true),
+                    });
+                }
+            }
+        }
+        declared_dependencies
+    }
+
+    // Returns the names of the dependencies in the given dependency tables,
+    // both at the top level and per target
+    fn get_dependency_names(&self, kinds: &[&str], only_optional: bool) -> HashSet<String> {
+        let mut tables: Vec<&toml::Value> = vec![&self.toml];
+        if let Some(targets) = self.toml.get("target").and_then(|t| t.as_table()) {
+            tables.extend(targets.values());
+        }
+        let dependency_tables: Vec<&toml::Value> = tables
+            .iter()
+            .flat_map(|table| kinds.iter().filter_map(move |kind| table.get(kind)))
+            .collect();
+
+        dependency_tables
+            .iter()
+            .filter_map(|dependencies| dependencies.as_table())
+            .flatten()
+            .filter(|(_name, dependency)| {
+                !only_optional
+                    || dependency
+                        .get("optional")
+                        .and_then(|optional| optional.as_bool())
+                        .unwrap_or(false)
+            })
+            .map(|(name, _dependency)| name.clone())
+            .collect()
+    }
+
     fn is_package_toml(&self) -> Result<bool> {
         Ok(self.toml.get("package").is_some())
     }
@@ -395,6 +711,179 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_toml_package_include_exclude() {
+        let parser = CargoTomlParser::from_content(
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\ninclude = [\"src/**/*.rs\", \"README.md\"]\n",
+            "Cargo.toml",
+        )
+        .unwrap();
+        assert_eq!(
+            parser.get_package_include().unwrap(),
+            vec!["src/**/*.rs".to_string(), "README.md".to_string()]
+        );
+        assert!(parser.get_package_exclude().unwrap().is_empty());
+        assert_eq!(parser.get_package_repository().unwrap(), None);
+
+        // depdive itself excludes its test resources
+        let parser = CargoTomlParser::new(Utf8Path::new("Cargo.toml")).unwrap();
+        assert_eq!(
+            parser.get_package_exclude().unwrap(),
+            vec!["resources/*".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_toml_feature_graph() {
+        let parser = CargoTomlParser::from_content(
+            indoc! {r#"
+                [package]
+                name = "a"
+                version = "0.1.0"
+
+                [dependencies]
+                log = "0.4"
+                serde = { version = "1", optional = true }
+                serde_json = { version = "1", optional = true }
+
+                [target.'cfg(unix)'.dependencies]
+                libc = { version = "0.2", optional = true }
+
+                [features]
+                default = ["std"]
+                std = ["serde?/std"]
+                json = ["serde", "dep:serde_json"]
+            "#},
+            "Cargo.toml",
+        )
+        .unwrap();
+        let feature_graph = parser.get_feature_graph().unwrap();
+
+        let to_vec = |values: &[&str]| -> Vec<String> {
+            values.iter().map(|value| value.to_string()).collect()
+        };
+        let mut expected: HashMap<String, Vec<String>> = HashMap::new();
+        expected.insert("default".to_string(), to_vec(&["std"]));
+        expected.insert("std".to_string(), to_vec(&["serde?/std"]));
+        expected.insert("json".to_string(), to_vec(&["serde", "dep:serde_json"]));
+        // implicit features of optional dependencies, but not of serde_json
+        expected.insert("serde".to_string(), to_vec(&["dep:serde"]));
+        expected.insert("libc".to_string(), to_vec(&["dep:libc"]));
+        assert_eq!(feature_graph, expected);
+
+        // no features without optional dependencies
+        let parser = CargoTomlParser::from_content(
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n",
+            "Cargo.toml",
+        )
+        .unwrap();
+        assert!(parser.get_feature_graph().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_toml_dependency_counts() {
+        let parser = CargoTomlParser::from_content(
+            indoc! {r#"
+                [package]
+                name = "a"
+                version = "0.1.0"
+
+                [dependencies]
+                log = "0.4"
+                serde = { version = "1", optional = true }
+
+                [build-dependencies]
+                cc = "1"
+                pkg-config = { version = "0.3", optional = true }
+
+                [dev-dependencies]
+                tempfile = "3"
+
+                [target.'cfg(unix)'.dependencies]
+                libc = "0.2"
+                log = "0.4"
+
+                [target.'cfg(windows)'.dev-dependencies]
+                winapi = "0.3"
+            "#},
+            "Cargo.toml",
+        )
+        .unwrap();
+
+        assert_eq!(
+            parser.get_dependencies(),
+            ["libc", "log", "serde"]
+                .iter()
+                .map(|dep| dep.to_string())
+                .collect()
+        );
+        assert_eq!(
+            parser.get_dependency_counts(),
+            DependencyCounts {
+                normal: 3,
+                build: 2,
+                dev: 2,
+                optional: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_toml_declared_dependencies() {
+        let get_declared_dependencies = |dependencies: &str| {
+            CargoTomlParser::from_content(
+                &format!(
+                    "[package]\nname = \"a\"\nversion = \"0.1.0\"\n{}",
+                    dependencies
+                ),
+                "Cargo.toml",
+            )
+            .unwrap()
+            .get_declared_dependencies()
+        };
+
+        let declared_dependencies = get_declared_dependencies(indoc! {r#"
+            [dependencies]
+            log = "0.4"
+
+            [target.'cfg(unix)'.dev-dependencies]
+            libc = { version = "0.2", default-features = false, features = ["extra_traits"] }
+        "#});
+        assert_eq!(
+            declared_dependencies.into_iter().collect::<Vec<_>>(),
+            vec![
+                DeclaredDependency {
+                    target: None,
+                    kind: "dependencies".to_string(),
+                    name: "log".to_string(),
+                    version: Some("0.4".to_string()),
+                    features: BTreeSet::new(),
+                    optional: false,
+                    default_features: true,
+                },
+                DeclaredDependency {
+                    target: Some("cfg(unix)".to_string()),
+                    kind: "dev-dependencies".to_string(),
+                    name: "libc".to_string(),
+                    version: Some("0.2".to_string()),
+                    features: vec!["extra_traits".to_string()].into_iter().collect(),
+                    optional: false,
+                    default_features: false,
+                },
+            ]
+        );
+
+        // a version string is the same as a table with only the version
+        assert_eq!(
+            get_declared_dependencies("[dependencies]\nlog = \"0.4\"\n"),
+            get_declared_dependencies("[dependencies]\nlog = { version = \"0.4\" }\n")
+        );
+        assert_ne!(
+            get_declared_dependencies("[dependencies]\nlog = \"0.4\"\n"),
+            get_declared_dependencies("[dependencies]\nlog = \"0.4.14\"\n")
+        );
+    }
+
     #[test]
     fn test_toml_invlaid_cargo_toml() {
         assert!(CargoTomlParser::new(Utf8Path::new("../Cargo.lock")).is_err());
@@ -484,5 +973,23 @@ mod test {
             CargoTomlParser::new(Utf8Path::new("resources/test/valid_dep/Cargo.toml")).unwrap();
         assert_eq!("valid_dep", toml_parser.get_package_name().unwrap());
         assert_eq!("0.1.0", toml_parser.get_package_version().unwrap());
+
+        let toml_parser = CargoTomlParser::from_content(
+            &read_to_string("resources/test/valid_dep/Cargo.toml").unwrap(),
+            "valid_dep/Cargo.toml",
+        )
+        .unwrap();
+        assert_eq!("valid_dep", toml_parser.get_package_name().unwrap());
+        assert_eq!(None, toml_parser.get_rust_version().unwrap());
+
+        let toml_parser = CargoTomlParser::from_content(
+            "[package]\nname = \"msrv\"\nversion = \"0.1.0\"\nrust-version = \"1.56\"\n",
+            "msrv/Cargo.toml",
+        )
+        .unwrap();
+        assert_eq!(
+            Some("1.56".to_string()),
+            toml_parser.get_rust_version().unwrap()
+        );
     }
 }
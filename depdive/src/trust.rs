@@ -0,0 +1,129 @@
+//! This module abstracts a configurable allowlist of trusted crates,
+//! e.g., an organization's own crates or crates from rust-lang,
+//! so that their updates can be auto-passed to reduce review noise.
+//!
+//! Trust only lowers the priority of soft signals, e.g., version diff stats.
+//! Below hard signals are always reported, even for a trusted crate:
+//! 1. Known advisories in the updated version
+//! 2. crates.io code not verified to match the git source, e.g., injected files,
+//!    or a release commit that could not be found
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use url::Url;
+
+use crate::diff::RECOGNIZED_REPOSITORY_HOSTS;
+
+/// An allowlist in the below toml format:
+/// ```toml
+/// crate_names = ["diem-*"] # `*` matches any sequence of characters
+/// owners = ["rust-lang", "git.example.com/team"] # repository owners, e.g., github.com/<owner>/<repo>,
+///                          # a bare owner matches on the recognized forge hosts only
+/// hosts = ["git.example.com"] # repository hosts
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrustAllowlist {
+    pub crate_names: Vec<String>,
+    pub owners: Vec<String>,
+    pub hosts: Vec<String>,
+}
+
+impl TrustAllowlist {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Checks if a crate is trusted by its name or its repository
+    pub fn is_trusted(&self, name: &str, repository: Option<&str>) -> Result<bool> {
+        for pattern in &self.crate_names {
+            if is_name_match(pattern, name)? {
+                return Ok(true);
+            }
+        }
+
+        let url = match repository.and_then(|repository| Url::parse(repository).ok()) {
+            Some(url) => url,
+            None => return Ok(false),
+        };
+        let host = match url.host_str() {
+            Some(host) => host,
+            None => return Ok(false),
+        };
+        if self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) {
+            return Ok(true);
+        }
+        if let Some(owner) = url.path_segments().and_then(|mut segments| segments.next()) {
+            // e.g., rust-lang on an arbitrary host is not the rust-lang of github.com
+            let is_recognized_host = RECOGNIZED_REPOSITORY_HOSTS
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case(host));
+            if self.owners.iter().any(|o| match o.split_once('/') {
+                Some((o_host, o_owner)) => {
+                    o_host.eq_ignore_ascii_case(host) && o_owner.eq_ignore_ascii_case(owner)
+                }
+                None => is_recognized_host && o.eq_ignore_ascii_case(owner),
+            }) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Checks if a crate name matches a pattern where `*` matches any sequence of characters
+pub(crate) fn is_name_match(pattern: &str, name: &str) -> Result<bool> {
+    let pattern = format!("^{}$", regex::escape(pattern).replace("\\*", ".*"));
+    Ok(Regex::new(&pattern)?.is_match(name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_trust_allowlist() {
+        let allowlist: TrustAllowlist = toml::from_str(
+            r#"
+            crate_names = ["diem-*", "guppy"]
+            owners = ["rust-lang", "git.internal.com/team"]
+            hosts = ["git.example.com"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(allowlist.is_trusted("diem-types", None).unwrap());
+        assert!(allowlist.is_trusted("guppy", None).unwrap());
+        assert!(!allowlist.is_trusted("guppy-summaries", None).unwrap());
+        assert!(!allowlist.is_trusted("diem", None).unwrap());
+
+        assert!(allowlist
+            .is_trusted("libc", Some("https://github.com/rust-lang/libc"))
+            .unwrap());
+        assert!(allowlist
+            .is_trusted("internal", Some("https://git.example.com/team/internal"))
+            .unwrap());
+        assert!(!allowlist
+            .is_trusted("tokio", Some("https://github.com/tokio-rs/tokio"))
+            .unwrap());
+
+        // An owner is scoped to its host
+        assert!(!allowlist
+            .is_trusted("libc", Some("https://evil.example/rust-lang/libc"))
+            .unwrap());
+        assert!(allowlist
+            .is_trusted("tool", Some("https://git.internal.com/team/tool"))
+            .unwrap());
+        assert!(!allowlist
+            .is_trusted("tool", Some("https://github.com/team/tool"))
+            .unwrap());
+
+        let allowlist = TrustAllowlist::default();
+        assert!(!allowlist
+            .is_trusted("libc", Some("https://github.com/rust-lang/libc"))
+            .unwrap());
+    }
+}
@@ -1,7 +1,8 @@
 //! This module abstracts analyses for dependency update review.
 
-use crate::cratesio::CratesioAnalyzer;
+use crate::cratesio::{CratesioAnalyzer, Registry, VersionYankStatus};
 use anyhow::{anyhow, Result};
+use camino::{Utf8Path, Utf8PathBuf};
 use geiger::RsFileMetrics;
 use git2::{build::CheckoutBuilder, Delta, Diff};
 use guppy::graph::{
@@ -19,13 +20,26 @@ use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
     ops::Sub,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 use url::Url;
 
 use crate::advisory::AdvisoryLookup;
-use crate::diff::{CrateSourceDiffReport, DiffAnalyzer, HeadCommitNotFoundError, VersionDiffInfo};
-use crate::guppy_wrapper::get_direct_dependencies;
+use crate::config::DiffConfig;
+use crate::diff::{
+    get_rust_module_path, is_msrv_increased, CloneLimiter, CrateSourceDiffReport,
+    DefaultFeaturesChange, DiffAnalyzer, DirectiveChange, HeadCommitNotFoundError, LineChange,
+    MacroChange, NetworkEndpoint, PublicApiChange, SecretFinding, VerificationStatus,
+    VersionDiffInfo,
+};
+use crate::docsrs::{get_offline_docsrs_report, DocsrsAnalyzer, DocsrsReport};
+use crate::guppy_wrapper::{get_all_dependencies, get_direct_dependencies};
+use crate::ossf::PackageOSSFReport;
+use crate::sink::OutputSink;
+use crate::source::SourceProvider;
+use crate::super_toml::CargoTomlParser;
+use crate::trust::{is_name_match, TrustAllowlist};
 
 #[derive(Debug, Clone)]
 pub enum DependencyType {
@@ -48,33 +62,840 @@ pub struct VersionSourceInfo {
     pub version: Version,
     pub repository: Option<String>,
     pub build_script_paths: HashSet<String>,
+    // features enabled for the package in the resolved graph, sorted
+    pub enabled_features: Vec<String>,
+    pub manifest_path: Option<Utf8PathBuf>, // None when the package is not in a graph
 }
 
 #[derive(Debug, Clone)]
 pub struct UpdateReviewReport {
     pub dep_update_review_reports: Vec<DepUpdateReviewReport>,
     pub version_conflicts: Vec<VersionConflict>,
+    pub introduced_dependencies: Vec<IntroducedDependency>,
+    // updates without findings left out of the output, still counted in the summary
+    pub omitted_clean_crates: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntroducedDependency {
+    pub name: String,
+    pub version: Version,
+    pub kind: IntroducedDependencyKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IntroducedDependencyKind {
+    NewCrate,   // the crate was not present in the prior graph at all
+    NewVersion, // the crate was present, but not with this version
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DepUpdateReviewReport {
     pub name: String,
     pub prior_version: VersionInfo,
     pub updated_version: VersionInfo,
     pub diff_stats: Option<VersionDiffStats>,
+    pub trusted: bool, // if the crate is on the trusted allowlist
 }
 
-#[derive(Debug, Clone)]
+/// Counts over a batch of dependency update reviews,
+/// with the names of the crates falling in each bucket
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub diverging: Vec<String>, // crates.io code differs from the git source
+    pub near_matches: Vec<String>, // crates.io code differs within the benign lines threshold
+    pub unanalyzed: Vec<String>, // crates.io code could not be compared with the git source
+    pub unsafe_additions: Vec<String>, // unsafe counters increased in a changed file
+    pub known_advisories: Vec<String>,
+    pub modified_build_scripts: Vec<String>,
+    pub auto_passed: Vec<String>, // trusted without any hard signal
+}
+
+impl BatchSummary {
+    pub fn from_reports(reports: &[DepUpdateReviewReport]) -> Self {
+        let mut summary = Self {
+            total: reports.len(),
+            ..Default::default()
+        };
+
+        for report in reports {
+            let name = &report.name;
+            match report
+                .updated_version
+                .crate_source_diff_report
+                .as_ref()
+                .map(|diff_report| diff_report.get_verification_status())
+            {
+                Some(VerificationStatus::Differs) => summary.diverging.push(name.clone()),
+                // the differing lines are still in the report to review
+                Some(VerificationStatus::NearMatch) => summary.near_matches.push(name.clone()),
+                Some(VerificationStatus::Matches) => {}
+                _ => summary.unanalyzed.push(name.clone()),
+            }
+
+            if report.has_unsafe_additions() {
+                summary.unsafe_additions.push(name.clone());
+            }
+            if report.has_modified_build_scripts() {
+                summary.modified_build_scripts.push(name.clone());
+            }
+
+            if !report.updated_version.known_advisories.is_empty() {
+                summary.known_advisories.push(name.clone());
+            }
+            if report.is_auto_passed() {
+                summary.auto_passed.push(name.clone());
+            }
+        }
+
+        for names in [
+            &mut summary.diverging,
+            &mut summary.near_matches,
+            &mut summary.unanalyzed,
+            &mut summary.unsafe_additions,
+            &mut summary.known_advisories,
+            &mut summary.modified_build_scripts,
+            &mut summary.auto_passed,
+        ] {
+            names.sort();
+            names.dedup();
+        }
+
+        summary
+    }
+
+    /// A one-line summary, e.g., for a CI log or a dashboard header
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} updates: {} diverging, {} near matches, {} unanalyzed, {} with unsafe additions, \
+            {} with known advisories, {} with modified build scripts, {} auto-passed",
+            self.total,
+            self.diverging.len(),
+            self.near_matches.len(),
+            self.unanalyzed.len(),
+            self.unsafe_additions.len(),
+            self.known_advisories.len(),
+            self.modified_build_scripts.len(),
+            self.auto_passed.len()
+        )
+    }
+}
+
+/// Changes in supply-chain posture between two batches of dependency update reviews,
+/// e.g., the ndjson reports of weekly runs, per crate name, each list sorted
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct BatchDelta {
+    pub added: Vec<String>,   // crates only in the new batch
+    pub removed: Vec<String>, // crates only in the old batch
+    pub newly_diverging: Vec<String>,
+    pub no_longer_diverging: Vec<String>,
+    // advisories not reported for the crate in the old batch, as (crate, advisory id)
+    pub new_advisories: Vec<(String, String)>,
+    // crates whose aggregate scorecard score dropped, as (crate, old score, new score)
+    pub ossf_score_drops: Vec<(String, f64, f64)>,
+}
+
+impl BatchDelta {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A batch of dependency update reviews, e.g., of a weekly run,
+/// with the scorecard reports of the crates, if fetched
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct BatchReport {
+    pub reviews: Vec<DepUpdateReviewReport>,
+    pub ossf_reports: Vec<PackageOSSFReport>,
+}
+
+/// Compares two batches of dependency update reviews, e.g., from `to_ndjson`,
+/// where a crate is diverging if its crates.io code differs from the git source
+/// in any of its reports, and its scorecard score is the lowest of its reports
+pub fn diff_batch_reports(old: &BatchReport, new: &BatchReport) -> BatchDelta {
+    let get_crates =
+        |reports: &[DepUpdateReviewReport]| -> HashMap<String, (bool, HashSet<String>)> {
+            let mut crates: HashMap<String, (bool, HashSet<String>)> = HashMap::new();
+            for report in reports {
+                let (diverging, advisories) = crates.entry(report.name.clone()).or_default();
+                *diverging |= report
+                    .updated_version
+                    .crate_source_diff_report
+                    .as_ref()
+                    .map(|diff_report| diff_report.get_verification_status())
+                    == Some(VerificationStatus::Differs);
+                advisories.extend(
+                    report
+                        .updated_version
+                        .known_advisories
+                        .iter()
+                        .map(|advisory| advisory.id.clone()),
+                );
+            }
+            crates
+        };
+    let old_crates = get_crates(&old.reviews);
+    let new_crates = get_crates(&new.reviews);
+    let get_scores = |reports: &[PackageOSSFReport]| -> HashMap<String, f64> {
+        let mut scores: HashMap<String, f64> = HashMap::new();
+        for report in reports {
+            if let Some(score) = report.ossf_report.as_ref().and_then(|r| r.score) {
+                let lowest = scores.entry(report.name.clone()).or_insert(score);
+                *lowest = lowest.min(score);
+            }
+        }
+        scores
+    };
+    let old_scores = get_scores(&old.ossf_reports);
+    let new_scores = get_scores(&new.ossf_reports);
+
+    let mut delta = BatchDelta::default();
+    for (name, (diverging, advisories)) in &new_crates {
+        let (was_diverging, old_advisories) = match old_crates.get(name) {
+            Some((was_diverging, old_advisories)) => (*was_diverging, Some(old_advisories)),
+            None => {
+                delta.added.push(name.clone());
+                (false, None)
+            }
+        };
+        if *diverging && !was_diverging {
+            delta.newly_diverging.push(name.clone());
+        } else if !diverging && was_diverging {
+            delta.no_longer_diverging.push(name.clone());
+        }
+        for advisory in advisories {
+            if !matches!(old_advisories, Some(old_advisories) if old_advisories.contains(advisory))
+            {
+                delta.new_advisories.push((name.clone(), advisory.clone()));
+            }
+        }
+    }
+    delta.removed = old_crates
+        .keys()
+        .filter(|name| !new_crates.contains_key(*name))
+        .cloned()
+        .collect();
+    delta.ossf_score_drops = new_scores
+        .iter()
+        .filter_map(|(name, score)| match old_scores.get(name) {
+            Some(old_score) if score < old_score => Some((name.clone(), *old_score, *score)),
+            _ => None,
+        })
+        .collect();
+
+    delta.added.sort();
+    delta.removed.sort();
+    delta.newly_diverging.sort();
+    delta.no_longer_diverging.sort();
+    delta.new_advisories.sort();
+    delta.ossf_score_drops.sort_by(|a, b| a.0.cmp(&b.0));
+    delta
+}
+
+impl UpdateReviewReport {
+    pub fn get_batch_summary(&self) -> BatchSummary {
+        let mut summary = BatchSummary::from_reports(&self.dep_update_review_reports);
+        summary.total += self.omitted_clean_crates.len();
+        summary
+    }
+
+    /// Leaves out the updates without findings (see `DepUpdateReviewReport::has_findings`)
+    /// from the output, e.g., for CI logs, while still counting them in the summary
+    pub fn retain_findings(&mut self) {
+        let (with_findings, clean): (Vec<_>, Vec<_>) = self
+            .dep_update_review_reports
+            .drain(..)
+            .partition(|report| report.has_findings());
+        self.dep_update_review_reports = with_findings;
+        self.omitted_clean_crates
+            .extend(clean.into_iter().map(|report| report.name));
+        self.omitted_clean_crates.sort();
+    }
+
+    // Returns the report for each updated dependency, ordered by name and version
+    fn get_sorted_reports(&self) -> Vec<&DepUpdateReviewReport> {
+        let mut reports: Vec<&DepUpdateReviewReport> =
+            self.dep_update_review_reports.iter().collect();
+        reports.sort_by(|a, b| {
+            (&a.name, &a.updated_version.version).cmp(&(&b.name, &b.updated_version.version))
+        });
+        reports
+    }
+
+    /// Writes the report of each updated dependency to a sink, ordered by name
+    pub fn write_to_sink(&self, sink: &dyn OutputSink) -> Result<()> {
+        for report in self.get_sorted_reports() {
+            sink.write_report(report)?;
+        }
+        Ok(())
+    }
+
+    /// Outputs the report as newline-delimited json,
+    /// i.e., one json object per updated dependency, ordered by name
+    pub fn to_ndjson(&self) -> Result<String> {
+        let reports = self.get_sorted_reports();
+        let mut ndjson = String::new();
+        for report in reports {
+            ndjson.push_str(&serde_json::to_string(report)?);
+            ndjson.push('\n');
+        }
+        Ok(ndjson)
+    }
+
+    /// Outputs the counts of the batch summary, and gauges per updated dependency
+    /// labeled by name and version, in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        // Counts the updates left out as without findings too
+        let summary = self.get_batch_summary();
+        let mut metrics = String::new();
+        let mut push_gauge = |name: &str, help: &str, samples: Vec<(String, usize)>| {
+            metrics.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} gauge\n",
+                name, help, name
+            ));
+            for (labels, value) in samples {
+                metrics.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+
+        for (name, help, count) in [
+            (
+                "whackadep_crates_total",
+                "Updated crates reviewed",
+                summary.total,
+            ),
+            (
+                "whackadep_crates_diverging",
+                "Updated crates whose crates.io code differs from the git source",
+                summary.diverging.len(),
+            ),
+            (
+                "whackadep_crates_near_matches",
+                "Updated crates whose crates.io code differs within the benign lines threshold",
+                summary.near_matches.len(),
+            ),
+            (
+                "whackadep_crates_unanalyzable",
+                "Updated crates whose crates.io code could not be compared with the git source",
+                summary.unanalyzed.len(),
+            ),
+            (
+                "whackadep_crates_unsafe_additions",
+                "Updated crates adding unsafe code",
+                summary.unsafe_additions.len(),
+            ),
+            (
+                "whackadep_crates_known_advisories",
+                "Updated crates with known advisories",
+                summary.known_advisories.len(),
+            ),
+            (
+                "whackadep_crates_modified_build_scripts",
+                "Updated crates changing a build script",
+                summary.modified_build_scripts.len(),
+            ),
+            (
+                "whackadep_crates_auto_passed",
+                "Updated crates trusted without any hard signal",
+                summary.auto_passed.len(),
+            ),
+        ] {
+            push_gauge(name, help, vec![(String::new(), count)]);
+        }
+
+        let reports = self.get_sorted_reports();
+        let get_labels = |report: &DepUpdateReviewReport| {
+            format!(
+                "{{name=\"{}\",version=\"{}\"}}",
+                escape_label_value(&report.name),
+                escape_label_value(&report.updated_version.version.to_string())
+            )
+        };
+        push_gauge(
+            "whackadep_crate_findings",
+            "1 if the update has findings to review",
+            reports
+                .iter()
+                .map(|report| (get_labels(report), report.has_findings() as usize))
+                .collect(),
+        );
+        push_gauge(
+            "whackadep_crate_risk_score",
+            "Signals of the update to review, see DepUpdateReviewReport::get_risk_score",
+            reports
+                .iter()
+                .map(|report| (get_labels(report), report.get_risk_score()))
+                .collect(),
+        );
+        push_gauge(
+            "whackadep_crate_known_advisories",
+            "Known advisories of the updated version",
+            reports
+                .iter()
+                .map(|report| {
+                    (
+                        get_labels(report),
+                        report.updated_version.known_advisories.len(),
+                    )
+                })
+                .collect(),
+        );
+        metrics
+    }
+
+    /// Outputs a row per updated dependency, ordered by name, for scanning in a terminal,
+    /// with the verification status of the crates.io code and the flags of the update,
+    /// where long crate names are truncated to keep the rows within a terminal width
+    pub fn to_table(&self) -> String {
+        let mut rows: Vec<[String; 5]> = vec![[
+            "CRATE".to_string(),
+            "UPDATE".to_string(),
+            "VERIFICATION".to_string(),
+            "FINDINGS".to_string(),
+            "FLAGS".to_string(),
+        ]];
+        for report in self.get_sorted_reports() {
+            let name = match report.name.chars().count() > MAX_TABLE_NAME_WIDTH {
+                true => format!(
+                    "{}…",
+                    report
+                        .name
+                        .chars()
+                        .take(MAX_TABLE_NAME_WIDTH - 1)
+                        .collect::<String>()
+                ),
+                false => report.name.clone(),
+            };
+            // offline, the crates.io code is not compared
+            let verification = match &report.updated_version.crate_source_diff_report {
+                Some(diff_report) => format!("{:?}", diff_report.get_verification_status()),
+                None => "-".to_string(),
+            };
+
+            let mut flags: Vec<&str> = Vec::new();
+            if report.has_unsafe_additions() {
+                flags.push("unsafe");
+            }
+            if report.has_modified_build_scripts() {
+                flags.push("build-script");
+            }
+            if !report.updated_version.known_advisories.is_empty() {
+                flags.push("advisories");
+            }
+            if let Some(stats) = &report.diff_stats {
+                if stats.msrv_increased {
+                    flags.push("msrv");
+                }
+                if stats.public_api_changed {
+                    flags.push("api");
+                }
+                if !stats.custom_findings.is_empty() {
+                    flags.push("custom");
+                }
+            }
+            if report.is_auto_passed() {
+                flags.push("auto-passed");
+            }
+
+            rows.push([
+                name,
+                format!(
+                    "{} -> {}",
+                    report.prior_version.version, report.updated_version.version
+                ),
+                verification,
+                if report.has_findings() { "yes" } else { "no" }.to_string(),
+                if flags.is_empty() {
+                    "-".to_string()
+                } else {
+                    flags.join(",")
+                },
+            ]);
+        }
+
+        let mut widths = [0; 5];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut table = String::new();
+        for row in &rows {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            table.push_str(line.join("  ").trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    /// Outputs per-file findings as GitHub Actions workflow commands,
+    /// e.g., `::error title=libc 0.2.93::src/x.rs: ...`,
+    /// for them to surface as annotations on pull requests
+    pub fn to_github_annotations(&self) -> String {
+        let reports = self.get_sorted_reports();
+        let mut annotations = String::new();
+        for report in reports {
+            let title = format!("{} {}", report.name, report.updated_version.version);
+            // the files are in the dependency, not the pull request checkout,
+            // so they go in the message instead of a file= GitHub cannot resolve
+            let mut annotate = |level: &str, file: &str, message: &str| {
+                annotations.push_str(&format!(
+                    "::{} title={}::{}\n",
+                    level,
+                    escape_annotation_property(&title),
+                    escape_annotation_data(&format!("{}: {}", file, message))
+                ));
+            };
+
+            if let Some(diff_report) = &report.updated_version.crate_source_diff_report {
+                if let Some(file_diff_stats) = &diff_report.file_diff_stats {
+                    for file in sorted(&file_diff_stats.files_added) {
+                        annotate(
+                            "error",
+                            file,
+                            "file in crates.io is not present in the git source",
+                        );
+                    }
+                    for file in sorted(&file_diff_stats.files_modified) {
+                        annotate(
+                            "error",
+                            file,
+                            "file in crates.io differs from the git source",
+                        );
+                    }
+                }
+                for file in &diff_report.published_ignored_files {
+                    annotate(
+                        "warning",
+                        file,
+                        "published file is excluded by the .gitignore of the git source",
+                    );
+                }
+            }
+
+            if let Some(diff_stats) = &report.diff_stats {
+                for stats in &diff_stats.unsafe_file_changed {
+                    if stats.unsafe_delta.has_addition() {
+                        annotate(
+                            "warning",
+                            &stats.file,
+                            &format!(
+                                "unsafe code added in the update from {}",
+                                report.prior_version.version
+                            ),
+                        );
+                    }
+                }
+                for secret in &diff_stats.new_secrets {
+                    for location in &secret.locations {
+                        annotate(
+                            "error",
+                            location,
+                            &format!("likely {} added in the update", secret.kind),
+                        );
+                    }
+                }
+            }
+        }
+        annotations
+    }
+}
+
+fn sorted(files: &HashSet<String>) -> Vec<&String> {
+    let mut files: Vec<&String> = files.iter().collect();
+    files.sort();
+    files
+}
+
+// Workflow command values are percent-encoded as in the GitHub Actions toolkit
+fn escape_annotation_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+// Escapes a label value of the Prometheus text exposition format
+fn escape_label_value(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_annotation_property(s: &str) -> String {
+    escape_annotation_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// The semver category of a version update,
+/// where an update to a pre-release, e.g., a release candidate, is categorized as such
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SemverUpdateKind {
+    Major,
+    Minor,
+    Patch,
+    PreRelease,
+}
+
+impl SemverUpdateKind {
+    pub fn new(old_version: &Version, new_version: &Version) -> Self {
+        if new_version.is_prerelease() {
+            SemverUpdateKind::PreRelease
+        } else if new_version.major != old_version.major {
+            SemverUpdateKind::Major
+        } else if new_version.minor != old_version.minor {
+            SemverUpdateKind::Minor
+        } else {
+            // including a release from a pre-release of the same version
+            SemverUpdateKind::Patch
+        }
+    }
+
+    /// Checks if the update may be breaking per semver,
+    /// where any change left to the first non-zero component is breaking,
+    /// as are pre-releases, which make no compatibility promises
+    pub fn is_breaking(old_version: &Version, new_version: &Version) -> bool {
+        match Self::new(old_version, new_version) {
+            SemverUpdateKind::Major | SemverUpdateKind::PreRelease => true,
+            SemverUpdateKind::Minor => old_version.major == 0,
+            SemverUpdateKind::Patch => {
+                old_version.major == 0
+                    && old_version.minor == 0
+                    && new_version.patch != old_version.patch
+            }
+        }
+    }
+}
+
+impl DepUpdateReviewReport {
+    /// Returns the semver category of the update
+    pub fn get_semver_update_kind(&self) -> SemverUpdateKind {
+        SemverUpdateKind::new(&self.prior_version.version, &self.updated_version.version)
+    }
+
+    /// Signals that are reported regardless of trust (see the trust module):
+    /// known advisories and crates.io code not verified to match the git source,
+    /// e.g., differing, or with the release commit not found
+    pub fn has_hard_signals(&self) -> bool {
+        // No report at all, e.g., offline, is not a signal on the crate itself
+        let is_source_unverified = matches!(
+            &self.updated_version.crate_source_diff_report,
+            Some(report) if report.get_verification_status() != VerificationStatus::Matches
+        );
+        !self.updated_version.known_advisories.is_empty() || is_source_unverified
+    }
+
+    fn has_unsafe_additions(&self) -> bool {
+        matches!(
+            &self.diff_stats,
+            Some(diff_stats) if diff_stats
+                .unsafe_file_changed
+                .iter()
+                .any(|stats| stats.unsafe_delta.has_addition())
+        )
+    }
+
+    fn has_modified_build_scripts(&self) -> bool {
+        matches!(
+            &self.diff_stats,
+            Some(diff_stats) if !diff_stats.modified_build_scripts.is_empty()
+        )
+    }
+
+    /// An update is clean, i.e., without findings, if its crates.io code matches
+    /// the git source, with the release tag and license agreeing with the published crate,
+    /// and it is not yanked, adds no unsafe or network endpoint, changes no build script,
+    /// default features, or MSRV upward, and has no known advisories or custom findings
+    pub fn has_findings(&self) -> bool {
+        self.get_risk_score() > 0
+    }
+
+    /// The number of the signals above the update has, 0 for a clean update,
+    /// e.g., to rank the updates of a batch or to chart a crate across runs,
+    /// where crates.io code differing in source files, e.g., Rust or C,
+    /// weighs as one more signal than differing in other files only
+    pub fn get_risk_score(&self) -> usize {
+        let diff_report = self.updated_version.crate_source_diff_report.as_ref();
+        let is_verified = matches!(
+            diff_report.map(|diff_report| diff_report.get_verification_status()),
+            Some(VerificationStatus::Matches)
+        );
+        let has_diff_report_signal = |signal: fn(&CrateSourceDiffReport) -> Option<bool>| {
+            diff_report.and_then(signal).unwrap_or(false)
+        };
+        let has_diff_stats_signal = |signal: fn(&VersionDiffStats) -> bool| {
+            self.diff_stats.as_ref().map(signal).unwrap_or(false)
+        };
+        [
+            !is_verified,
+            has_diff_report_signal(|diff_report| diff_report.is_source_different),
+            self.diff_stats.is_none(),
+            has_diff_report_signal(|diff_report| diff_report.vcs_info_tag_mismatch),
+            has_diff_report_signal(|diff_report| diff_report.license_declaration_mismatch),
+            matches!(
+                &self.updated_version.yank_status,
+                Some(yank_status) if yank_status.is_yanked
+            ),
+            !self.updated_version.known_advisories.is_empty(),
+            self.has_unsafe_additions(),
+            self.has_modified_build_scripts(),
+            has_diff_stats_signal(|stats| stats.msrv_increased),
+            has_diff_stats_signal(|stats| !stats.default_features_changes.is_empty()),
+            has_diff_stats_signal(|stats| !stats.new_network_endpoints.is_empty()),
+            has_diff_stats_signal(|stats| !stats.new_secrets.is_empty()),
+            has_diff_stats_signal(|stats| !stats.custom_findings.is_empty()),
+        ]
+        .iter()
+        .filter(|signal| **signal)
+        .count()
+    }
+
+    /// A trusted crate update needs no review unless there is a hard signal
+    pub fn is_auto_passed(&self) -> bool {
+        self.trusted && !self.has_hard_signals()
+    }
+
+    /// A one-sentence summary of the notable signals of the update,
+    /// e.g., "This update modifies 4 files (+120/-30) and adds 2 unsafe usages."
+    /// Clean signals are left out
+    pub fn summarize(&self) -> String {
+        let plural = |count: usize, singular: &str, plural: &str| {
+            format!("{} {}", count, if count == 1 { singular } else { plural })
+        };
+
+        let mut clauses: Vec<String> = Vec::new();
+        match &self.diff_stats {
+            Some(stats) => {
+                if !stats.files_changed.is_empty() {
+                    clauses.push(format!(
+                        "modifies {} (+{}/-{})",
+                        plural(stats.files_changed.len(), "file", "files"),
+                        stats.insertions,
+                        stats.deletions
+                    ));
+                }
+
+                let unsafe_additions: i64 = stats
+                    .unsafe_file_changed
+                    .iter()
+                    .map(|file| {
+                        let delta = &file.unsafe_delta;
+                        [
+                            delta.functions,
+                            delta.expressions,
+                            delta.impls,
+                            delta.traits,
+                            delta.methods,
+                        ]
+                        .iter()
+                        .filter(|count| **count > 0)
+                        .sum::<i64>()
+                    })
+                    .sum();
+                if unsafe_additions > 0 {
+                    clauses.push(format!(
+                        "adds {}",
+                        plural(unsafe_additions as usize, "unsafe usage", "unsafe usages")
+                    ));
+                }
+
+                if !stats.modified_build_scripts.is_empty() {
+                    let mut paths: Vec<&str> = stats
+                        .modified_build_scripts
+                        .iter()
+                        .map(|path| path.as_str())
+                        .collect();
+                    paths.sort_unstable();
+                    let noun = if paths.len() == 1 {
+                        "build script"
+                    } else {
+                        "build scripts"
+                    };
+                    clauses.push(format!("changes the {} {}", noun, paths.join(", ")));
+                }
+
+                if stats.msrv_increased {
+                    match &stats.msrv_change {
+                        Some((_old, Some(new))) => clauses.push(format!(
+                            "raises the minimum supported Rust version to {}",
+                            new
+                        )),
+                        _ => clauses.push("raises the minimum supported Rust version".to_string()),
+                    }
+                }
+                if stats.public_api_changed {
+                    clauses.push("changes the public API".to_string());
+                }
+                if stats.dependency_tree_unchanged == Some(false) {
+                    clauses.push("changes the declared dependencies".to_string());
+                }
+                if !stats.default_features_changes.is_empty() {
+                    clauses.push("changes the default features".to_string());
+                }
+                if !stats.new_network_endpoints.is_empty() {
+                    clauses.push("references new network endpoints".to_string());
+                }
+                if !stats.new_secrets.is_empty() {
+                    clauses.push("adds likely secrets".to_string());
+                }
+                if !stats.custom_findings.is_empty() {
+                    clauses.push(format!(
+                        "has {}",
+                        plural(
+                            stats.custom_findings.len(),
+                            "custom finding",
+                            "custom findings"
+                        )
+                    ));
+                }
+            }
+            None => clauses.push("could not be diffed against the prior version".to_string()),
+        }
+
+        if matches!(
+            &self.updated_version.crate_source_diff_report,
+            Some(report) if report.is_different == Some(true)
+        ) {
+            clauses.push("differs on crates.io from the git source".to_string());
+        }
+        let advisories = self.updated_version.known_advisories.len();
+        if advisories > 0 {
+            clauses.push(format!(
+                "has {}",
+                plural(advisories, "known advisory", "known advisories")
+            ));
+        }
+
+        match clauses.as_slice() {
+            [] => "This update has no notable changes.".to_string(),
+            [clause] => format!("This update {}.", clause),
+            [clauses @ .., last] => {
+                let separator = if clauses.len() > 1 { ", and " } else { " and " };
+                format!("This update {}{}{}.", clauses.join(", "), separator, last)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VersionInfo {
     pub name: String,
     pub version: Version,
-    pub downloads: u64,
+    pub downloads: Option<u64>, // None when crates.io is unavailable, e.g., offline
     pub crate_source_diff_report: Option<CrateSourceDiffReport>, // We can optionally present this report
     // based on the use case
     pub known_advisories: Vec<CrateVersionRustSecAdvisory>,
+    // None unless looked up, or if the manifest of the version is unavailable
+    pub docsrs_report: Option<DocsrsReport>,
+    pub yank_status: Option<VersionYankStatus>, // None when crates.io is unavailable
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct CrateVersionRustSecAdvisory {
     pub id: String,
     pub title: String,
@@ -86,7 +907,7 @@ pub struct VersionChangeInfo {
     pub new_version: Option<Version>, // None when a dep is removed
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct VersionDiffStats {
     pub files_changed: HashSet<String>,
     pub rust_files_changed: u64,
@@ -94,6 +915,68 @@ pub struct VersionDiffStats {
     pub deletions: u64,
     pub modified_build_scripts: HashSet<String>, // Empty indicates no change in build scripts
     pub unsafe_file_changed: Vec<FileUnsafeChangeStats>,
+    pub package_renamed_from: Option<String>, // prior name if renamed within the update
+    // files with the most lines changed, i.e., the files to review first
+    pub largest_file_changes: Vec<(String, LineChange)>,
+    pub msrv_change: Option<(Option<String>, Option<String>)>, // (old, new) rust-version if changed
+    pub msrv_increased: bool,
+    // cfg and nightly feature attributes added or removed, per file
+    pub directive_changes: Vec<DirectiveChange>,
+    // a heuristic for breaking changes, regardless of the semver label of the update
+    pub public_api_changed: bool,
+    pub public_api_changes: Vec<PublicApiChange>,
+    // macro_rules! definitions added, removed, or modified, by name
+    pub macro_changes: Vec<MacroChange>,
+    // rust files changed only in formatting or the order of items
+    pub non_substantive_changes: Vec<String>,
+    // changed source files with no related tests, a heuristic for risky changes
+    pub untested_changed_files: Vec<String>,
+    // no dependency added, removed, or changed in version or features,
+    // i.e., a source-only update, None if the Cargo.toml is missing in either version
+    pub dependency_tree_unchanged: Option<bool>,
+    // dependencies declared only in the new version, besides dev dependencies
+    pub added_dependencies: Vec<String>,
+    // changed rust files with the paths of the modules they define, sorted by file
+    pub changed_rust_modules: Vec<(String, String)>,
+    pub default_features_changes: Vec<DefaultFeaturesChange>,
+    // urls and host:port pairs in string literals of added rust code
+    pub new_network_endpoints: Vec<NetworkEndpoint>,
+    // likely secrets, e.g., access tokens, in the added lines of any file
+    pub new_secrets: Vec<SecretFinding>,
+    // reported by the diff post-processors registered with the analyzer
+    pub custom_findings: Vec<Finding>,
+}
+
+/// A finding of a custom check on a version diff, e.g., an org specific one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub check: String, // name of the check, e.g., "internal-allowlist"
+    pub message: String,
+    pub file: Option<String>,
+}
+
+/// A custom check run on the version diff of an update after the standard analyses
+pub type DiffPostProcessor = Box<dyn Fn(&VersionDiffInfo) -> Vec<Finding>>;
+
+/// What a reviewer has to trust anew in updating a dependency,
+/// e.g., across a major version, consolidated from the version diff and crates.io
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrustDelta {
+    pub name: String,
+    pub old_version: Version,
+    pub new_version: Version,
+    pub semver_update_kind: SemverUpdateKind,
+    // dependencies declared only in the new version, besides dev dependencies
+    pub added_dependencies: Vec<String>,
+    pub unsafe_file_changed: Vec<FileUnsafeChangeStats>,
+    pub modified_build_scripts: HashSet<String>,
+    pub public_api_changes: Vec<PublicApiChange>,
+    pub msrv_change: Option<(Option<String>, Option<String>)>, // (old, new) rust-version if changed
+    pub msrv_increased: bool,
+    // crates.io users who published the versions, None if unavailable, e.g., offline
+    pub old_publisher: Option<String>,
+    pub new_publisher: Option<String>,
+    pub publisher_changed: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -107,7 +990,7 @@ pub enum VersionConflict {
     },
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum FileUnsafeCodeChangeStatus {
     UnsafeCounterModified, // when we have a delta in unsafe counter
     NoUnsafeCode,          // changed file(s) contained no unsafe code before and after change
@@ -116,9 +999,10 @@ pub enum FileUnsafeCodeChangeStatus {
                            // TODO: our tool isn't that smart yet to verify if unsafe code lines have been changed
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FileUnsafeChangeStats {
     pub file: String,
+    #[serde(with = "delta_serde")]
     pub change_type: Delta,
     pub unsafe_change_status: FileUnsafeCodeChangeStatus,
     pub unsafe_delta: UnsafeDelta, // Delta in Unsafe counter:
@@ -128,9 +1012,48 @@ pub struct FileUnsafeChangeStats {
 
     // Below field indicate the post state of an added/modified file
     // and will be None in case of a deleted file
+    // Note: geiger metrics are not serializable, hence skipped in serialized reports
+    #[serde(skip)]
     pub unsafe_status: Option<RsFileMetrics>,
 }
 
+// (De)serializes git2::Delta as its variant name
+mod delta_serde {
+    use git2::Delta;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    const DELTAS: [(Delta, &str); 11] = [
+        (Delta::Unmodified, "Unmodified"),
+        (Delta::Added, "Added"),
+        (Delta::Deleted, "Deleted"),
+        (Delta::Modified, "Modified"),
+        (Delta::Renamed, "Renamed"),
+        (Delta::Copied, "Copied"),
+        (Delta::Ignored, "Ignored"),
+        (Delta::Untracked, "Untracked"),
+        (Delta::Typechange, "Typechange"),
+        (Delta::Unreadable, "Unreadable"),
+        (Delta::Conflicted, "Conflicted"),
+    ];
+
+    pub fn serialize<S: Serializer>(delta: &Delta, serializer: S) -> Result<S::Ok, S::Error> {
+        let (_, name) = DELTAS
+            .iter()
+            .find(|(d, _)| d == delta)
+            .ok_or_else(|| serde::ser::Error::custom("unknown git delta"))?;
+        serializer.serialize_str(name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Delta, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        DELTAS
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(d, _)| *d)
+            .ok_or_else(|| D::Error::custom(format!("unknown git delta: {}", name)))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct UnsafeDelta {
     pub functions: i64,
@@ -141,6 +1064,14 @@ pub struct UnsafeDelta {
 }
 
 impl UnsafeDelta {
+    pub fn has_addition(&self) -> bool {
+        self.functions > 0
+            || self.expressions > 0
+            || self.impls > 0
+            || self.traits > 0
+            || self.methods > 0
+    }
+
     pub fn has_no_change(&self) -> bool {
         self.expressions == 0
             && self.functions == 0
@@ -164,15 +1095,174 @@ impl Sub for UnsafeDelta {
     }
 }
 
+// Total files to highlight as the largest changes in a version diff, unless configured
+const DEFAULT_LARGEST_FILE_CHANGES_COUNT: usize = 5;
+
+// Characters of a crate name shown in a table row, beyond which the name is truncated
+const MAX_TABLE_NAME_WIDTH: usize = 24;
+
+// The build script cargo picks up when the manifest does not declare one
+const DEFAULT_BUILD_SCRIPT_PATH: &str = "build.rs";
+
 pub struct UpdateAnalyzer {
     // the key will be crate name, old version, and updated version
     cache: RefCell<HashMap<(String, Version, Version), DepUpdateReviewReport>>,
+    allowlist: TrustAllowlist,
+    // crates to skip from the analysis, matched by name where `*` matches any sequence
+    exclude_patterns: Vec<String>,
+    // if set, only the crates with these names are analyzed
+    only_crates: Option<Vec<String>>,
+    // shared by the diff analyzers of a batch to bound the git clones in flight
+    clone_limiter: Arc<CloneLimiter>,
+    // set in offline mode, where the sources are read locally instead of crates.io
+    source_provider: Option<Box<dyn SourceProvider>>,
+    // a local copy of the advisory database to use instead of fetching one
+    advisory_db_path: Option<PathBuf>,
+    // if the docs.rs build status of the updated versions is looked up
+    docsrs_lookup: bool,
+    // total files to highlight as the largest changes in a version diff
+    largest_file_changes_count: usize,
+    // applied to every diff analyzer created for the batch
+    diff_config: DiffConfig,
+    // the registry the crates are looked up in and downloaded from
+    registry: Registry,
+    // custom checks run on every version diff, in the order registered
+    diff_post_processors: Vec<DiffPostProcessor>,
 }
 
 impl UpdateAnalyzer {
     pub fn new() -> Self {
         Self {
             cache: RefCell::new(HashMap::new()),
+            allowlist: TrustAllowlist::default(),
+            exclude_patterns: Vec::new(),
+            only_crates: None,
+            clone_limiter: Arc::new(CloneLimiter::default()),
+            source_provider: None,
+            advisory_db_path: None,
+            docsrs_lookup: false,
+            largest_file_changes_count: DEFAULT_LARGEST_FILE_CHANGES_COUNT,
+            diff_config: DiffConfig::default(),
+            registry: Registry::default(),
+            diff_post_processors: Vec::new(),
+        }
+    }
+
+    /// Sets the allowlist of trusted crates whose updates can be auto-passed
+    pub fn set_allowlist(&mut self, allowlist: TrustAllowlist) -> &mut Self {
+        self.allowlist = allowlist;
+        self
+    }
+
+    /// Sets the patterns for crate names to exclude from the analysis,
+    /// e.g., first-party crates, or "diem-*" for a family of crates
+    pub fn set_exclude_patterns(&mut self, patterns: &[&str]) -> &mut Self {
+        self.exclude_patterns = patterns.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// Limits the analysis to the crates with the given names,
+    /// e.g., for a targeted re-review, on top of the exclude patterns
+    pub fn set_only_crates(&mut self, names: &[&str]) -> &mut Self {
+        self.only_crates = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    /// Sets the max git clones in flight across the batch,
+    /// separate from requests to crates.io
+    pub fn set_max_concurrent_clones(&mut self, max_concurrent_clones: usize) -> &mut Self {
+        self.clone_limiter = Arc::new(CloneLimiter::new(max_concurrent_clones));
+        self
+    }
+
+    /// Sets the offline mode, e.g., for air-gapped runs,
+    /// where the version diffs and repository urls come from the given local sources
+    /// and crates.io is not reached, leaving the crates.io sections of the report unavailable
+    pub fn set_offline(&mut self, source_provider: Box<dyn SourceProvider>) -> &mut Self {
+        self.source_provider = Some(source_provider);
+        self
+    }
+
+    /// Sets a local copy of the advisory database to look up advisories in,
+    /// required for advisories in offline mode
+    pub fn set_advisory_db_path(&mut self, path: &Path) -> &mut Self {
+        self.advisory_db_path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Sets if the docs.rs configuration of the updated versions is checked
+    /// against the enabled features, and their docs.rs build status looked up,
+    /// which is skipped in offline mode
+    pub fn set_docsrs_lookup(&mut self, docsrs_lookup: bool) -> &mut Self {
+        self.docsrs_lookup = docsrs_lookup;
+        self
+    }
+
+    /// Sets the total files to highlight as the largest changes in a version diff
+    pub fn set_largest_file_changes_count(&mut self, count: usize) -> &mut Self {
+        self.largest_file_changes_count = count;
+        self
+    }
+
+    /// Sets the options of the diff analyzers, e.g., from a config file
+    pub fn set_diff_config(&mut self, diff_config: DiffConfig) -> &mut Self {
+        self.diff_config = diff_config;
+        self
+    }
+
+    /// Sets the crates.io compatible registry, e.g., an enterprise mirror,
+    /// for every lookup and download of the batch
+    pub fn set_registry(&mut self, registry: Registry) -> &mut Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Registers a custom check, e.g., flagging a change to an internal allowlist module,
+    /// run on the version diff of every update, whose findings go into the report
+    pub fn register_diff_post_processor(
+        &mut self,
+        post_processor: impl Fn(&VersionDiffInfo) -> Vec<Finding> + 'static,
+    ) -> &mut Self {
+        self.diff_post_processors.push(Box::new(post_processor));
+        self
+    }
+
+    fn get_diff_analyzer(&self) -> Result<DiffAnalyzer> {
+        let mut diff_analyzer = DiffAnalyzer::new()?;
+        self.diff_config
+            .apply_to_diff_analyzer(&mut diff_analyzer)?;
+        diff_analyzer
+            .set_clone_limiter(self.clone_limiter.clone())
+            .set_registry(self.registry.clone());
+        Ok(diff_analyzer)
+    }
+
+    pub(crate) fn is_excluded(&self, name: &str) -> Result<bool> {
+        if let Some(only_crates) = &self.only_crates {
+            if !only_crates.iter().any(|only| only == name) {
+                return Ok(true);
+            }
+        }
+        for pattern in &self.exclude_patterns {
+            if is_name_match(pattern, name)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // A crate named to analyze that is in neither graph is likely a typo,
+    // but the rest of the analysis is still useful
+    fn warn_missing_only_crates(&self, prior_graph: &PackageGraph, post_graph: &PackageGraph) {
+        let only_crates = match &self.only_crates {
+            Some(only_crates) => only_crates,
+            None => return,
+        };
+        for name in only_crates {
+            let in_graph = |graph: &PackageGraph| graph.packages().any(|pkg| pkg.name() == name);
+            if !in_graph(prior_graph) && !in_graph(post_graph) {
+                tracing::warn!("crate {} is not in the dependency graph", name);
+            }
         }
     }
 
@@ -200,14 +1290,44 @@ impl UpdateAnalyzer {
         cargo_opts: &CargoOptions,
         feature_filter: impl FeatureFilter<'a>,
     ) -> Result<UpdateReviewReport> {
-        // Get the changed dependency stats
-        let dep_change_infos =
-            Self::compare_pacakge_graphs(prior_graph, post_graph, cargo_opts, feature_filter)?;
+        self.analyze_updates_with_sink(prior_graph, post_graph, cargo_opts, feature_filter, None)
+    }
 
-        // Filter version updates
-        let updated_deps: Vec<DependencyChangeInfo> = dep_change_infos
-            .iter()
-            .filter(
+    /// Given two guppy graph
+    /// determines the updated dependencies,
+    /// and writes the review of each to a sink as soon as it completes,
+    /// e.g., for streaming consumers, before the report of the whole batch
+    pub fn analyze_updates_to_sink(
+        self,
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+        sink: &dyn OutputSink,
+    ) -> Result<UpdateReviewReport> {
+        self.analyze_updates_with_sink(
+            prior_graph,
+            post_graph,
+            &Self::get_default_cargo_options(),
+            StandardFeatures::All,
+            Some(sink),
+        )
+    }
+
+    fn analyze_updates_with_sink<'a>(
+        self,
+        prior_graph: &'a PackageGraph,
+        post_graph: &'a PackageGraph,
+        cargo_opts: &CargoOptions,
+        feature_filter: impl FeatureFilter<'a>,
+        sink: Option<&dyn OutputSink>,
+    ) -> Result<UpdateReviewReport> {
+        // Get the changed dependency stats
+        let dep_change_infos =
+            Self::compare_pacakge_graphs(prior_graph, post_graph, cargo_opts, feature_filter)?;
+
+        // Filter version updates
+        let updated_deps: Vec<DependencyChangeInfo> = dep_change_infos
+            .iter()
+            .filter(
                 |dep| match (dep.old_version_info.as_ref(), dep.new_version_info.as_ref()) {
                     (Some(old), Some(new)) => new.version > old.version,
                     _ => false,
@@ -217,11 +1337,26 @@ impl UpdateAnalyzer {
             .collect();
         // TODO: add reporting for version downgrades, add, and remove
 
+        // Skip excluded crates before any network or git work
+        self.warn_missing_only_crates(prior_graph, post_graph);
+        let mut included_deps: Vec<DependencyChangeInfo> = Vec::new();
+        for dep in updated_deps {
+            if !self.is_excluded(&dep.name)? {
+                included_deps.push(dep);
+            }
+        }
+        let updated_deps = included_deps;
+
         // clean cache if there's anything in a weird scenario
         // And store all the distinct update review in the cache
         self.cache.borrow_mut().clear();
         for dep in &updated_deps {
-            self.get_update_review(dep)?;
+            let cached_reports = self.cache.borrow().len();
+            let report = self.get_update_review(dep)?;
+            // a review already in the cache has been written
+            if let Some(sink) = sink.filter(|_| self.cache.borrow().len() > cached_reports) {
+                sink.write_report(&report)?;
+            }
         }
         let dep_update_review_reports: Vec<DepUpdateReviewReport> =
             self.cache.borrow_mut().drain().map(|(_k, v)| v).collect();
@@ -229,12 +1364,145 @@ impl UpdateAnalyzer {
         let version_conflicts: Vec<VersionConflict> =
             Self::determine_version_conflict(&updated_deps, post_graph);
 
+        let mut introduced_dependencies: Vec<IntroducedDependency> = Vec::new();
+        for dep in Self::get_introduced_dependencies(prior_graph, post_graph) {
+            if !self.is_excluded(&dep.name)? {
+                introduced_dependencies.push(dep);
+            }
+        }
+
         Ok(UpdateReviewReport {
             dep_update_review_reports,
             version_conflicts,
+            introduced_dependencies,
+            omitted_clean_crates: Vec::new(),
+        })
+    }
+
+    /// Given two guppy graph, prior and post,
+    /// returns the dependencies, direct or transitive,
+    /// that are present only in the post graph, i.e.,
+    /// the new code to be trusted after the update
+    pub fn get_introduced_dependencies(
+        prior_graph: &PackageGraph,
+        post_graph: &PackageGraph,
+    ) -> Vec<IntroducedDependency> {
+        let prior_deps = get_all_dependencies(prior_graph);
+        let prior_names: HashSet<&str> = prior_deps.iter().map(|dep| dep.name()).collect();
+        let prior_versions: HashSet<(&str, &Version)> = prior_deps
+            .iter()
+            .map(|dep| (dep.name(), dep.version()))
+            .collect();
+
+        let mut introduced_dependencies: Vec<IntroducedDependency> =
+            get_all_dependencies(post_graph)
+                .iter()
+                .filter(|dep| !prior_versions.contains(&(dep.name(), dep.version())))
+                .map(|dep| IntroducedDependency {
+                    name: dep.name().to_string(),
+                    version: dep.version().clone(),
+                    kind: if prior_names.contains(dep.name()) {
+                        IntroducedDependencyKind::NewVersion
+                    } else {
+                        IntroducedDependencyKind::NewCrate
+                    },
+                })
+                .collect();
+        introduced_dependencies.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        introduced_dependencies.dedup();
+
+        introduced_dependencies
+    }
+
+    /// Returns the trust delta of updating a crate from the old to the new version,
+    /// diffing the two from crates.io, or else from the given repository,
+    /// or from the local sources in offline mode, where the publishers are unavailable
+    /// Without a package graph, the build script is checked at the default build.rs
+    pub fn trust_delta(
+        &self,
+        name: &str,
+        old: &Version,
+        new: &Version,
+        repository: Option<&str>,
+    ) -> Result<TrustDelta> {
+        let get_version_source_info = |version: &Version| VersionSourceInfo {
+            version: version.clone(),
+            repository: repository.map(|repository| repository.to_string()),
+            build_script_paths: vec![DEFAULT_BUILD_SCRIPT_PATH.to_string()]
+                .into_iter()
+                .collect(),
+            enabled_features: Vec::new(),
+            manifest_path: None,
+        };
+        let dep_change_info = DependencyChangeInfo {
+            name: name.to_string(),
+            dep_type: DependencyType::Host,
+            old_version_info: Some(get_version_source_info(old)),
+            new_version_info: Some(get_version_source_info(new)),
+        };
+
+        let (diff_stats, publishers) = match &self.source_provider {
+            Some(source_provider) => (
+                self.analyze_local_version_diff(&dep_change_info, source_provider.as_ref())?,
+                None,
+            ),
+            None => (
+                self.analyze_version_diff(&dep_change_info)?,
+                Some(self.get_publishers(name, old, new)),
+            ),
+        };
+        let diff_stats = diff_stats.ok_or_else(|| {
+            anyhow!(
+                "no diff found between {} versions {} and {}",
+                name,
+                old,
+                new
+            )
+        })?;
+        let (old_publisher, new_publisher) = publishers.unwrap_or((None, None));
+
+        Ok(TrustDelta {
+            name: name.to_string(),
+            old_version: old.clone(),
+            new_version: new.clone(),
+            semver_update_kind: SemverUpdateKind::new(old, new),
+            added_dependencies: diff_stats.added_dependencies,
+            unsafe_file_changed: diff_stats.unsafe_file_changed,
+            modified_build_scripts: diff_stats.modified_build_scripts,
+            public_api_changes: diff_stats.public_api_changes,
+            msrv_change: diff_stats.msrv_change,
+            msrv_increased: diff_stats.msrv_increased,
+            publisher_changed: matches!(
+                (&old_publisher, &new_publisher),
+                (Some(old_publisher), Some(new_publisher)) if old_publisher != new_publisher
+            ),
+            old_publisher,
+            new_publisher,
         })
     }
 
+    // Looks up the publishers of the old and the new version on the registry,
+    // None for a version whose lookup fails, e.g., published before crates.io
+    // recorded publishers, as a change of publisher only adds context to the review
+    fn get_publishers(
+        &self,
+        name: &str,
+        old: &Version,
+        new: &Version,
+    ) -> (Option<String>, Option<String>) {
+        let cratesio_analyzer = match CratesioAnalyzer::for_registry(&self.registry) {
+            Ok(cratesio_analyzer) => cratesio_analyzer,
+            Err(_e) => return (None, None),
+        };
+        let get_publisher = |version: &Version| {
+            cratesio_analyzer
+                .get_version_publisher(name, version)
+                .ok()
+                .flatten()
+        };
+        (get_publisher(old), get_publisher(new))
+    }
+
     fn determine_version_conflict(
         dep_change_infos: &[DependencyChangeInfo],
         graph: &PackageGraph,
@@ -339,10 +1607,14 @@ impl UpdateAnalyzer {
                     build_script_paths.insert(x);
                 });
 
+            let manifest_path =
+                Self::get_manifest_path_from_graph(prior_graph, &name, &old_version);
             old_version_info = Some(VersionSourceInfo {
                 version: old_version,
                 repository,
                 build_script_paths,
+                enabled_features: Vec::new(), // only the new version is checked against its docs
+                manifest_path,
             });
         }
 
@@ -357,10 +1629,13 @@ impl UpdateAnalyzer {
                     build_script_paths.insert(x);
                 });
 
+            let manifest_path = Self::get_manifest_path_from_graph(post_graph, &name, &new_version);
             new_version_info = Some(VersionSourceInfo {
                 version: new_version,
                 repository,
                 build_script_paths,
+                enabled_features: Self::get_enabled_features_from_summarydiff(summary_diff_status),
+                manifest_path,
             })
         }
 
@@ -423,6 +1698,68 @@ impl UpdateAnalyzer {
         }
     }
 
+    // Returns the features enabled for the package after the change, sorted
+    fn get_enabled_features_from_summarydiff(
+        summary_diff_status: &SummaryDiffStatus,
+    ) -> Vec<String> {
+        match summary_diff_status {
+            SummaryDiffStatus::Added { info } => info.features.iter().cloned().collect(),
+            SummaryDiffStatus::Modified {
+                added_features,
+                unchanged_features,
+                ..
+            } => {
+                let mut enabled_features: Vec<String> = added_features
+                    .union(unchanged_features)
+                    .map(|feature| feature.to_string())
+                    .collect();
+                enabled_features.sort();
+                enabled_features
+            }
+            SummaryDiffStatus::Removed { .. } => Vec::new(),
+        }
+    }
+
+    // A graph may have multiple versions of a crate, e.g., a major version bump
+    // next to a transitive dependency on the prior one
+    fn get_manifest_path_from_graph(
+        graph: &PackageGraph,
+        crate_name: &str,
+        version: &Version,
+    ) -> Option<Utf8PathBuf> {
+        let package = graph
+            .packages()
+            .find(|p| p.name() == crate_name && p.version() == version)?;
+        Some(package.manifest_path().to_path_buf())
+    }
+
+    // Checks the docs.rs configuration in the manifest against the enabled features,
+    // and looks up the docs.rs build unless offline
+    // The docs are informational, so an unparsable manifest gives no report,
+    // and a docs.rs outage only leaves the build status unknown
+    fn get_docsrs_report(
+        &self,
+        name: &str,
+        version: &Version,
+        manifest_path: &Utf8Path,
+        enabled_features: &[String],
+        offline: bool,
+    ) -> Option<DocsrsReport> {
+        let toml_parser = CargoTomlParser::new(manifest_path).ok()?;
+        let enabled_features: Vec<&str> = enabled_features
+            .iter()
+            .map(|feature| feature.as_str())
+            .collect();
+        let mut docsrs_report =
+            get_offline_docsrs_report(name, version, &toml_parser, &enabled_features).ok()?;
+        if !offline {
+            docsrs_report.build_succeeded = DocsrsAnalyzer::new()
+                .and_then(|docsrs_analyzer| docsrs_analyzer.get_build_status(name, version))
+                .unwrap_or(None);
+        }
+        Some(docsrs_report)
+    }
+
     fn get_repository_from_graph(graph: &PackageGraph, crate_name: &str) -> Option<String> {
         let package = graph.packages().find(|p| p.name() == crate_name)?;
         let repository = package.repository()?.to_string();
@@ -451,47 +1788,115 @@ impl UpdateAnalyzer {
                 return Ok(report);
             }
 
-            let cratesio_analyzer = CratesioAnalyzer::new()?;
-            let advisory_lookup = AdvisoryLookup::new()?;
+            // In offline mode, crates.io is not reached, and the advisories
+            // are looked up only if a local advisory database is set
+            let offline = self.source_provider.is_some();
+            let cratesio_analyzer = match offline {
+                true => None,
+                false => Some(CratesioAnalyzer::for_registry(&self.registry)?),
+            };
+            let advisory_lookup = match (&self.advisory_db_path, offline) {
+                (Some(path), _) => Some(AdvisoryLookup::from_path(path)?),
+                (None, false) => Some(AdvisoryLookup::new()?),
+                (None, true) => None,
+            };
+            let get_known_advisories =
+                |version: &Version| -> Result<Vec<CrateVersionRustSecAdvisory>> {
+                    Ok(match &advisory_lookup {
+                        Some(advisory_lookup) => advisory_lookup
+                            .get_crate_version_advisories(name, &version.to_string())?
+                            .iter()
+                            .filter(|advisory| advisory.metadata.withdrawn.is_none())
+                            .map(|advisory| Self::get_crate_version_rustsec_advisory(advisory))
+                            .collect(),
+                        None => Vec::new(),
+                    })
+                };
+            let get_downloads = |version: &Version| -> Result<Option<u64>> {
+                cratesio_analyzer
+                    .as_ref()
+                    .map(|cratesio_analyzer| cratesio_analyzer.get_version_downloads(name, version))
+                    .transpose()
+            };
+            // A registry that does not report yanks, e.g., a mirror, leaves the status unknown
+            // instead of failing the review; it is from the same version endpoint
+            // as the downloads, cached by the analyzer
+            let get_yank_status = |version: &Version| -> Option<VersionYankStatus> {
+                cratesio_analyzer.as_ref().and_then(|cratesio_analyzer| {
+                    cratesio_analyzer
+                        .get_version_yank_status(name, version)
+                        .ok()
+                })
+            };
+            let mut diff_analyzer = self.get_diff_analyzer()?;
+            // Without the publish time the gap from the release commit is left out of the diff
+            if let Some(published_at) = cratesio_analyzer.as_ref().and_then(|cratesio_analyzer| {
+                cratesio_analyzer
+                    .get_version_publish_time(name, new_version)
+                    .ok()
+            }) {
+                diff_analyzer.set_publish_time(name, &new_version.to_string(), published_at);
+            }
 
             let prior_version = VersionInfo {
                 name: name.clone(),
                 version: old_version.clone(),
-                downloads: cratesio_analyzer.get_version_downloads(name, old_version)?,
+                downloads: get_downloads(old_version)?,
                 crate_source_diff_report: None, // We do not need to do this heavy calculation
                 // for the old_version in the update report
-                known_advisories: advisory_lookup
-                    .get_crate_version_advisories(name, &old_version.to_string())?
-                    .iter()
-                    .filter(|advisory| advisory.metadata.withdrawn.is_none())
-                    .map(|advisory| Self::get_crate_version_rustsec_advisory(advisory))
-                    .collect(),
+                known_advisories: get_known_advisories(old_version)?,
+                docsrs_report: None,
+                yank_status: get_yank_status(old_version),
             };
 
             let updated_version = VersionInfo {
                 name: name.clone(),
                 version: new_version.clone(),
-                downloads: cratesio_analyzer.get_version_downloads(name, new_version)?,
-                crate_source_diff_report: Some(DiffAnalyzer::new()?.analyze_crate_source_diff(
-                    name,
-                    &new_version.to_string(),
-                    new_version_info.repository.as_deref(),
-                )?),
-                known_advisories: advisory_lookup
-                    .get_crate_version_advisories(name, &new_version.to_string())?
-                    .iter()
-                    .filter(|advisory| advisory.metadata.withdrawn.is_none())
-                    .map(|advisory| Self::get_crate_version_rustsec_advisory(advisory))
-                    .collect(),
+                downloads: get_downloads(new_version)?,
+                // comparing with crates.io code is unavailable offline
+                crate_source_diff_report: match offline {
+                    true => None,
+                    false => Some(diff_analyzer.analyze_crate_source_diff(
+                        name,
+                        &new_version.to_string(),
+                        new_version_info.repository.as_deref(),
+                    )?),
+                },
+                known_advisories: get_known_advisories(new_version)?,
+                docsrs_report: match (&new_version_info.manifest_path, self.docsrs_lookup) {
+                    (Some(manifest_path), true) => self.get_docsrs_report(
+                        name,
+                        new_version,
+                        manifest_path,
+                        &new_version_info.enabled_features,
+                        offline,
+                    ),
+                    _ => None,
+                },
+                yank_status: get_yank_status(new_version),
             };
 
-            let diff_stats = Self::analyze_version_diff(dep_change_info)?;
+            let (diff_stats, repository) = match &self.source_provider {
+                Some(source_provider) => (
+                    self.analyze_local_version_diff(dep_change_info, source_provider.as_ref())?,
+                    Self::get_repository_from_source_dir(
+                        &source_provider.get_source_dir(name, new_version)?,
+                    )?,
+                ),
+                None => (
+                    self.analyze_version_diff(dep_change_info)?,
+                    new_version_info.repository.clone(),
+                ),
+            };
+
+            let trusted = self.allowlist.is_trusted(name, repository.as_deref())?;
 
             let report = DepUpdateReviewReport {
                 name: dep_change_info.name.clone(),
                 prior_version,
                 updated_version,
                 diff_stats,
+                trusted,
             };
             self.cache.borrow_mut().insert(key.clone(), report);
             self.get_update_review_report_from_cache(&key)
@@ -514,6 +1919,7 @@ impl UpdateAnalyzer {
     }
 
     fn analyze_version_diff(
+        &self,
         dep_change_info: &DependencyChangeInfo,
     ) -> Result<Option<VersionDiffStats>> {
         if let (name, Some(old_version_info), Some(new_version_info)) = (
@@ -532,7 +1938,7 @@ impl UpdateAnalyzer {
                 // Get version diff info from crates.io if avalaiable on crates.io
                 let version_diff_info = diff_analyzer
                     .get_version_diff_info_between_repos(&repo_old_version, &repo_new_version)?;
-                Ok(Some(Self::get_version_diff_stats(
+                Ok(Some(self.get_version_diff_stats(
                     dep_change_info,
                     &version_diff_info,
                 )?))
@@ -554,7 +1960,7 @@ impl UpdateAnalyzer {
                         }
                     }
                 };
-                Ok(Some(Self::get_version_diff_stats(
+                Ok(Some(self.get_version_diff_stats(
                     dep_change_info,
                     &version_diff_info,
                 )?))
@@ -567,7 +1973,51 @@ impl UpdateAnalyzer {
         }
     }
 
+    // Diffs the two versions from their local sources, without network access
+    fn analyze_local_version_diff(
+        &self,
+        dep_change_info: &DependencyChangeInfo,
+        source_provider: &dyn SourceProvider,
+    ) -> Result<Option<VersionDiffStats>> {
+        if let (name, Some(old_version_info), Some(new_version_info)) = (
+            &dep_change_info.name,
+            &dep_change_info.old_version_info,
+            &dep_change_info.new_version_info,
+        ) {
+            let old_version = &old_version_info.version;
+            let new_version = &new_version_info.version;
+            let diff_analyzer = DiffAnalyzer::new()?;
+
+            let repo_old_version = diff_analyzer.get_git_repo_for_source_dir(
+                name,
+                &old_version.to_string(),
+                &source_provider.get_source_dir(name, old_version)?,
+            )?;
+            let repo_new_version = diff_analyzer.get_git_repo_for_source_dir(
+                name,
+                &new_version.to_string(),
+                &source_provider.get_source_dir(name, new_version)?,
+            )?;
+            let version_diff_info = diff_analyzer
+                .get_version_diff_info_between_repos(&repo_old_version, &repo_new_version)?;
+            Ok(Some(self.get_version_diff_stats(
+                dep_change_info,
+                &version_diff_info,
+            )?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Reads the repository url from the Cargo.toml in a crate source directory
+    fn get_repository_from_source_dir(source_dir: &Path) -> Result<Option<String>> {
+        let toml_path = Utf8PathBuf::from_path_buf(source_dir.join("Cargo.toml"))
+            .map_err(|path| anyhow!("non utf-8 path: {:?}", path))?;
+        CargoTomlParser::new(&toml_path)?.get_package_repository()
+    }
+
     fn get_version_diff_stats(
+        &self,
         dep_change_info: &DependencyChangeInfo,
         version_diff_info: &VersionDiffInfo,
     ) -> Result<VersionDiffStats> {
@@ -602,7 +2052,16 @@ impl UpdateAnalyzer {
             .map(|path| path.to_string())
             .collect();
 
+        let mut changed_rust_modules: Vec<(String, String)> = files_changed
+            .iter()
+            .filter_map(|path| {
+                get_rust_module_path(path).map(|module_path| (path.clone(), module_path))
+            })
+            .collect();
+        changed_rust_modules.sort();
+
         let files_unsafe_change_stats = Self::analyze_unsafe_changes_in_diff(version_diff_info)?;
+        let public_api_changes = version_diff_info.get_public_api_changes()?;
 
         Ok(VersionDiffStats {
             files_changed,
@@ -616,6 +2075,31 @@ impl UpdateAnalyzer {
                     report.unsafe_change_status != FileUnsafeCodeChangeStatus::NoUnsafeCode
                 })
                 .collect(),
+            package_renamed_from: version_diff_info.package_renamed_from.clone(),
+            largest_file_changes: version_diff_info
+                .get_largest_file_changes(self.largest_file_changes_count)?,
+            msrv_change: version_diff_info.msrv_change.clone(),
+            msrv_increased: matches!(
+                &version_diff_info.msrv_change,
+                Some((old, new)) if is_msrv_increased(old.as_deref(), new.as_deref())
+            ),
+            directive_changes: version_diff_info.get_directive_changes()?,
+            public_api_changed: !public_api_changes.is_empty(),
+            public_api_changes,
+            macro_changes: version_diff_info.get_macro_changes()?,
+            non_substantive_changes: version_diff_info.get_non_substantive_changes()?,
+            untested_changed_files: version_diff_info.get_untested_changed_files()?,
+            dependency_tree_unchanged: version_diff_info.dependency_tree_unchanged,
+            added_dependencies: version_diff_info.get_added_dependencies()?,
+            changed_rust_modules,
+            default_features_changes: version_diff_info.default_features_changes.clone(),
+            new_network_endpoints: version_diff_info.get_new_network_endpoints()?,
+            new_secrets: version_diff_info.get_new_secrets()?,
+            custom_findings: self
+                .diff_post_processors
+                .iter()
+                .flat_map(|post_processor| post_processor(version_diff_info))
+                .collect(),
         })
     }
 
@@ -789,18 +2273,35 @@ impl Default for UpdateAnalyzer {
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::{
-        DependencyType, DiffAnalyzer, FileUnsafeCodeChangeStatus, PackageGraph, StandardFeatures,
-        UpdateAnalyzer, VersionConflict::DirectTransitiveVersionConflict,
+        diff_batch_reports, escape_label_value, BatchReport, BatchSummary,
+        CrateVersionRustSecAdvisory, DepUpdateReviewReport, DependencyChangeInfo, DependencyType,
+        DiffAnalyzer, FileUnsafeChangeStats, FileUnsafeCodeChangeStatus, Finding,
+        IntroducedDependencyKind, PackageGraph, SemverUpdateKind, StandardFeatures, UnsafeDelta,
+        UpdateAnalyzer, UpdateReviewReport, VersionConflict::DirectTransitiveVersionConflict,
+        VersionDiffStats, VersionInfo, VersionSourceInfo,
+    };
+    use crate::cratesio::{Registry, VersionYankStatus};
+    use crate::diff::{
+        trim_remote_url, CrateSourceDiffReport, DefaultFeaturesChange, FileDiffStats,
+        NetworkEndpoint, SecretFinding, VerificationStatus,
     };
-    use crate::diff::trim_remote_url;
+    use crate::ossf::{OSSFRepo, OSSFReport, PackageOSSFReport};
+    use crate::sink::test::MemorySink;
+    use crate::source::SourceProvider;
+    use crate::trust::TrustAllowlist;
+    use anyhow::{anyhow, Result};
+    use git2::{Delta, Repository, Signature};
     use guppy::{CargoMetadata, MetadataCommand};
     use once_cell::sync::Lazy;
     use semver::Version;
     use serial_test::serial;
-    use std::path::PathBuf;
+    use std::collections::{HashMap, HashSet};
+    use std::path::{Path, PathBuf};
     use std::sync::Once;
+    use tempfile::tempdir;
+    use url::Url;
 
     struct PackageGraphPair {
         prior: PackageGraph,
@@ -949,6 +2450,1257 @@ mod test {
                 .filter(|dep| dep.old_version_info.is_some() && dep.new_version_info.is_some())
                 .count()
         );
+
+        // The enabled features and the manifest of the new version are kept
+        // for checking the docs.rs build against
+        let git2_version_info = dep_change_infos
+            .iter()
+            .find(|dep| dep.name == "git2")
+            .and_then(|dep| dep.new_version_info.as_ref())
+            .unwrap();
+        assert!(git2_version_info
+            .enabled_features
+            .contains(&"https".to_string()));
+        assert!(git2_version_info
+            .manifest_path
+            .as_ref()
+            .unwrap()
+            .ends_with("git2-0.13.20/Cargo.toml"));
+    }
+
+    #[test]
+    fn test_update_exclude_patterns() {
+        let graphs = get_test_graph_pair_guppy();
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer.set_exclude_patterns(&["guppy", "cargo_*", "libgit2-*"]);
+
+        // Both updated crates are excluded, so nothing is fetched for them
+        let report = update_analyzer
+            .analyze_updates(&graphs.prior, &graphs.post)
+            .unwrap();
+        assert!(report.dep_update_review_reports.is_empty());
+        assert!(report.version_conflicts.is_empty());
+
+        let names: Vec<&str> = report
+            .introduced_dependencies
+            .iter()
+            .map(|dep| dep.name.as_str())
+            .collect();
+        assert!(names.contains(&"git2"));
+        assert!(!names.contains(&"guppy"));
+        assert!(!names.contains(&"cargo_metadata"));
+        assert!(!names.contains(&"libgit2-sys"));
+    }
+
+    #[test]
+    fn test_update_only_crates() {
+        let graphs = get_test_graph_pair_guppy();
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer.set_only_crates(&["git2", "not-in-graph"]);
+
+        // Neither updated crate is named, so nothing is fetched for them
+        let report = update_analyzer
+            .analyze_updates(&graphs.prior, &graphs.post)
+            .unwrap();
+        assert!(report.dep_update_review_reports.is_empty());
+
+        let names: Vec<&str> = report
+            .introduced_dependencies
+            .iter()
+            .map(|dep| dep.name.as_str())
+            .collect();
+        assert!(!names.is_empty());
+        assert!(names.iter().all(|name| *name == "git2"));
+
+        // Only guppy has its sources served, so cargo_metadata, also updated,
+        // would fail the review unless skipped
+        let dir = tempdir().unwrap();
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer
+            .set_only_crates(&["guppy"])
+            .set_offline(Box::new(get_test_guppy_source_provider(dir.path())));
+        let report = update_analyzer
+            .analyze_updates(&graphs.prior, &graphs.post)
+            .unwrap();
+        let names: Vec<&str> = report
+            .dep_update_review_reports
+            .iter()
+            .map(|report| report.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["guppy"]);
+        assert_eq!(
+            report.dep_update_review_reports[0]
+                .diff_stats
+                .as_ref()
+                .unwrap()
+                .files_changed,
+            vec!["Cargo.toml".to_string()].into_iter().collect()
+        );
+    }
+
+    // Serves the sources of guppy 0.8.0 and 0.9.0, differing only in the version
+    fn get_test_guppy_source_provider(dir: &Path) -> TestSourceProvider {
+        let mut source_dirs: HashMap<Version, PathBuf> = HashMap::new();
+        for version in &["0.8.0", "0.9.0"] {
+            let source_dir = dir.join(format!("guppy-{}", version));
+            std::fs::create_dir_all(source_dir.join("src")).unwrap();
+            std::fs::write(
+                source_dir.join("Cargo.toml"),
+                format!("[package]\nname = \"guppy\"\nversion = \"{}\"\n", version),
+            )
+            .unwrap();
+            std::fs::write(source_dir.join("src/lib.rs"), "").unwrap();
+            source_dirs.insert(Version::parse(version).unwrap(), source_dir);
+        }
+        TestSourceProvider { source_dirs }
+    }
+
+    #[test]
+    fn test_update_largest_file_changes_count() {
+        let graphs = get_test_graph_pair_guppy();
+        let dir = tempdir().unwrap();
+        let get_largest_file_changes = |count: Option<usize>| {
+            let mut update_analyzer = get_test_update_analyzer();
+            update_analyzer
+                .set_only_crates(&["guppy"])
+                .set_offline(Box::new(get_test_guppy_source_provider(dir.path())));
+            if let Some(count) = count {
+                update_analyzer.set_largest_file_changes_count(count);
+            }
+            update_analyzer
+                .analyze_updates(&graphs.prior, &graphs.post)
+                .unwrap()
+                .dep_update_review_reports[0]
+                .diff_stats
+                .as_ref()
+                .unwrap()
+                .largest_file_changes
+                .iter()
+                .map(|(file, _change)| file.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(get_largest_file_changes(None), vec!["Cargo.toml"]);
+        assert!(get_largest_file_changes(Some(0)).is_empty());
+    }
+
+    #[test]
+    fn test_update_analyze_updates_to_sink() {
+        let graphs = get_test_graph_pair_guppy();
+        let dir = tempdir().unwrap();
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer
+            .set_only_crates(&["guppy"])
+            .set_offline(Box::new(get_test_guppy_source_provider(dir.path())));
+        let sink = MemorySink::default();
+        let report = update_analyzer
+            .analyze_updates_to_sink(&graphs.prior, &graphs.post, &sink)
+            .unwrap();
+
+        // each distinct review is written once, the same as in the batch report
+        let reports = sink.reports.borrow();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].name, "guppy");
+        assert_eq!(
+            serde_json::to_string(&reports[0]).unwrap(),
+            report.to_ndjson().unwrap().trim_end()
+        );
+    }
+
+    #[test]
+    fn test_update_introduced_dependencies() {
+        let graphs = get_test_graph_pair_guppy();
+        let introduced = UpdateAnalyzer::get_introduced_dependencies(&graphs.prior, &graphs.post);
+
+        let get_names = |kind: IntroducedDependencyKind| -> Vec<String> {
+            introduced
+                .iter()
+                .filter(|dep| dep.kind == kind)
+                .map(|dep| format!("{}:{}", dep.name, dep.version))
+                .collect()
+        };
+        assert_eq!(
+            get_names(IntroducedDependencyKind::NewVersion),
+            vec!["cargo_metadata:0.13.1", "guppy:0.9.0"]
+        );
+        assert_eq!(
+            get_names(IntroducedDependencyKind::NewCrate),
+            vec![
+                "git2:0.13.20",
+                "jobserver:0.1.22",
+                "libgit2-sys:0.12.21+1.1.0",
+                "libssh2-sys:0.2.21",
+                "libz-sys:1.1.3"
+            ]
+        );
+    }
+
+    fn get_test_version_info(
+        name: &str,
+        version: &str,
+        injected_file: Option<&str>,
+    ) -> VersionInfo {
+        VersionInfo {
+            name: name.to_string(),
+            version: Version::parse(version).unwrap(),
+            downloads: None,
+            crate_source_diff_report: Some(CrateSourceDiffReport {
+                name: name.to_string(),
+                version: version.to_string(),
+                is_different: Some(injected_file.is_some()),
+                file_diff_stats: Some(FileDiffStats {
+                    files_added: injected_file.iter().map(|f| f.to_string()).collect(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            known_advisories: Vec::new(),
+            docsrs_report: None,
+            yank_status: None,
+        }
+    }
+
+    // An update from 0.1.0 to 0.1.1 without any finding in the diff,
+    // the crates.io source of which differs from git if a file is injected
+    pub(crate) fn get_test_report(
+        name: &str,
+        injected_file: Option<&str>,
+    ) -> DepUpdateReviewReport {
+        DepUpdateReviewReport {
+            name: name.to_string(),
+            prior_version: get_test_version_info(name, "0.1.0", None),
+            updated_version: get_test_version_info(name, "0.1.1", injected_file),
+            diff_stats: Some(VersionDiffStats::default()),
+            trusted: false,
+        }
+    }
+
+    // Serves crate sources from directories on disk
+    struct TestSourceProvider {
+        source_dirs: HashMap<Version, PathBuf>,
+    }
+
+    impl SourceProvider for TestSourceProvider {
+        fn get_source_dir(&self, name: &str, version: &Version) -> Result<PathBuf> {
+            self.source_dirs
+                .get(version)
+                .cloned()
+                .ok_or_else(|| anyhow!("no source for {} {}", name, version))
+        }
+    }
+
+    #[test]
+    fn test_update_offline_review() {
+        let dir = tempdir().unwrap();
+        let mut source_dirs: HashMap<Version, PathBuf> = HashMap::new();
+        for (version, lib) in &[("0.1.0", "pub fn a() {}\n"), ("0.1.1", "pub fn b() {}\n")] {
+            let source_dir = dir.path().join(format!("offline-crate-{}", version));
+            std::fs::create_dir_all(source_dir.join("src")).unwrap();
+            std::fs::write(
+                source_dir.join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"offline-crate\"\nversion = \"{}\"\nrepository = \"https://github.com/diem/offline-crate\"\n",
+                    version
+                ),
+            )
+            .unwrap();
+            std::fs::write(source_dir.join("src/lib.rs"), lib).unwrap();
+            source_dirs.insert(Version::parse(version).unwrap(), source_dir);
+        }
+
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer
+            .set_allowlist(TrustAllowlist {
+                owners: vec!["diem".to_string()],
+                ..Default::default()
+            })
+            .set_offline(Box::new(TestSourceProvider { source_dirs }));
+        let get_version_source_info = |version: &str| VersionSourceInfo {
+            version: Version::parse(version).unwrap(),
+            repository: None,
+            build_script_paths: HashSet::new(),
+            enabled_features: Vec::new(),
+            manifest_path: None,
+        };
+        let report = update_analyzer
+            .get_update_review(&DependencyChangeInfo {
+                name: "offline-crate".to_string(),
+                dep_type: DependencyType::Host,
+                old_version_info: Some(get_version_source_info("0.1.0")),
+                new_version_info: Some(get_version_source_info("0.1.1")),
+            })
+            .unwrap();
+
+        // crates.io sections are unavailable
+        assert!(report.prior_version.downloads.is_none());
+        assert!(report.updated_version.downloads.is_none());
+        assert!(report.updated_version.crate_source_diff_report.is_none());
+
+        let diff_stats = report.diff_stats.unwrap();
+        assert_eq!(
+            diff_stats.files_changed,
+            vec!["Cargo.toml".to_string(), "src/lib.rs".to_string()]
+                .into_iter()
+                .collect()
+        );
+        assert!(diff_stats.public_api_changed);
+        // the repository is read from the local Cargo.toml
+        assert!(report.trusted);
+    }
+
+    #[test]
+    fn test_update_diff_post_processor() {
+        let dir = tempdir().unwrap();
+        let mut source_dirs: HashMap<Version, PathBuf> = HashMap::new();
+        for (version, allowlist) in &[
+            ("0.1.0", "pub const A: &[&str] = &[];\n"),
+            ("0.1.1", "pub const A: &[&str] = &[\"x\"];\n"),
+        ] {
+            let source_dir = dir.path().join(format!("processed-crate-{}", version));
+            std::fs::create_dir_all(source_dir.join("src")).unwrap();
+            std::fs::write(
+                source_dir.join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"processed-crate\"\nversion = \"{}\"\n",
+                    version
+                ),
+            )
+            .unwrap();
+            std::fs::write(source_dir.join("src/lib.rs"), "pub mod allowlist;\n").unwrap();
+            std::fs::write(source_dir.join("src/allowlist.rs"), allowlist).unwrap();
+            source_dirs.insert(Version::parse(version).unwrap(), source_dir);
+        }
+
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer
+            .set_offline(Box::new(TestSourceProvider { source_dirs }))
+            .register_diff_post_processor(|version_diff_info| {
+                version_diff_info
+                    .diff
+                    .deltas()
+                    .filter_map(|delta| delta.new_file().path())
+                    .filter(|path| path.ends_with("allowlist.rs"))
+                    .map(|path| Finding {
+                        check: "internal-allowlist".to_string(),
+                        message: "changes the allowlist module".to_string(),
+                        file: Some(path.to_string_lossy().to_string()),
+                    })
+                    .collect()
+            })
+            .register_diff_post_processor(|_version_diff_info| Vec::new());
+        let get_version_source_info = |version: &str| VersionSourceInfo {
+            version: Version::parse(version).unwrap(),
+            repository: None,
+            build_script_paths: HashSet::new(),
+            enabled_features: Vec::new(),
+            manifest_path: None,
+        };
+        let report = update_analyzer
+            .get_update_review(&DependencyChangeInfo {
+                name: "processed-crate".to_string(),
+                dep_type: DependencyType::Host,
+                old_version_info: Some(get_version_source_info("0.1.0")),
+                new_version_info: Some(get_version_source_info("0.1.1")),
+            })
+            .unwrap();
+
+        assert_eq!(
+            report.diff_stats.as_ref().unwrap().custom_findings,
+            vec![Finding {
+                check: "internal-allowlist".to_string(),
+                message: "changes the allowlist module".to_string(),
+                file: Some("src/allowlist.rs".to_string()),
+            }]
+        );
+        assert!(report.has_findings());
+        assert!(report.summarize().contains("has 1 custom finding"));
+    }
+
+    #[test]
+    fn test_update_trust_delta() {
+        let dir = tempdir().unwrap();
+        let mut source_dirs: HashMap<Version, PathBuf> = HashMap::new();
+        let versions = [
+            (
+                "0.9.0",
+                "rust-version = \"1.50\"\n[dependencies]\nlog = \"0.4\"\n\
+                 [dev-dependencies]\ncriterion = \"0.3\"\n",
+                "pub fn a() {}\n",
+                None,
+            ),
+            (
+                "1.0.0",
+                "rust-version = \"1.60\"\n[dependencies]\nlog = \"0.4\"\nrand = \"0.8\"\n\
+                 [build-dependencies]\ncc = \"1.0\"\n\
+                 [dev-dependencies]\ncriterion = \"0.3\"\nproptest = \"1.0\"\n",
+                "pub fn a(x: *const u8) -> u8 {\n    unsafe { *x }\n}\n",
+                Some("fn main() {}\n"),
+            ),
+        ];
+        for (version, manifest, lib, build_script) in &versions {
+            let source_dir = dir.path().join(format!("bumped-crate-{}", version));
+            std::fs::create_dir_all(source_dir.join("src")).unwrap();
+            std::fs::write(
+                source_dir.join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"bumped-crate\"\nversion = \"{}\"\n{}",
+                    version, manifest
+                ),
+            )
+            .unwrap();
+            std::fs::write(source_dir.join("src/lib.rs"), lib).unwrap();
+            if let Some(build_script) = build_script {
+                std::fs::write(source_dir.join("build.rs"), build_script).unwrap();
+            }
+            source_dirs.insert(Version::parse(version).unwrap(), source_dir);
+        }
+
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer.set_offline(Box::new(TestSourceProvider { source_dirs }));
+        let trust_delta = update_analyzer
+            .trust_delta(
+                "bumped-crate",
+                &Version::parse("0.9.0").unwrap(),
+                &Version::parse("1.0.0").unwrap(),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(trust_delta.semver_update_kind, SemverUpdateKind::Major);
+        // dev dependencies are not built for dependents
+        assert_eq!(trust_delta.added_dependencies, vec!["cc", "rand"]);
+        assert_eq!(
+            trust_delta.modified_build_scripts,
+            vec!["build.rs".to_string()].into_iter().collect()
+        );
+        assert_eq!(trust_delta.unsafe_file_changed.len(), 1);
+        assert_eq!(trust_delta.unsafe_file_changed[0].file, "src/lib.rs");
+        assert!(trust_delta.unsafe_file_changed[0]
+            .unsafe_delta
+            .has_addition());
+        assert_eq!(trust_delta.public_api_changes.len(), 1);
+        assert_eq!(
+            trust_delta.msrv_change,
+            Some((Some("1.50".to_string()), Some("1.60".to_string())))
+        );
+        assert!(trust_delta.msrv_increased);
+        // crates.io is not reached offline
+        assert_eq!(trust_delta.old_publisher, None);
+        assert_eq!(trust_delta.new_publisher, None);
+        assert!(!trust_delta.publisher_changed);
+
+        // the versions must be available to diff
+        assert!(update_analyzer
+            .trust_delta(
+                "bumped-crate",
+                &Version::parse("0.9.0").unwrap(),
+                &Version::parse("2.0.0").unwrap(),
+                None,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_publishers_unavailable() {
+        // nothing listens on the discard port, so the registry is unreachable
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer.set_registry(Registry {
+            api_base_url: "http://127.0.0.1:9/api/v1".to_string(),
+            token: None,
+        });
+        assert_eq!(
+            update_analyzer.get_publishers(
+                "bumped-crate",
+                &Version::parse("0.9.0").unwrap(),
+                &Version::parse("1.0.0").unwrap()
+            ),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_update_semver_update_kind() {
+        let get_kind = |old: &str, new: &str| -> (SemverUpdateKind, bool) {
+            let (old, new) = (Version::parse(old).unwrap(), Version::parse(new).unwrap());
+            (
+                SemverUpdateKind::new(&old, &new),
+                SemverUpdateKind::is_breaking(&old, &new),
+            )
+        };
+
+        assert_eq!(get_kind("1.2.3", "2.0.0"), (SemverUpdateKind::Major, true));
+        assert_eq!(get_kind("1.2.3", "1.3.0"), (SemverUpdateKind::Minor, false));
+        assert_eq!(get_kind("0.2.3", "0.3.0"), (SemverUpdateKind::Minor, true));
+        assert_eq!(get_kind("1.2.3", "1.2.4"), (SemverUpdateKind::Patch, false));
+        assert_eq!(get_kind("0.0.3", "0.0.4"), (SemverUpdateKind::Patch, true));
+
+        // release candidates
+        assert_eq!(
+            get_kind("0.14.0", "1.0.0-rc.1"),
+            (SemverUpdateKind::PreRelease, true)
+        );
+        assert_eq!(
+            get_kind("1.0.0-rc.1", "1.0.0-rc.2"),
+            (SemverUpdateKind::PreRelease, true)
+        );
+        assert_eq!(
+            get_kind("1.0.0-rc.2", "1.0.0"),
+            (SemverUpdateKind::Patch, false)
+        );
+        // a pre-release precedes its release
+        assert!(Version::parse("1.0.0-rc.2").unwrap() < Version::parse("1.0.0").unwrap());
+    }
+
+    #[test]
+    fn test_update_trusted_crate_with_injected_file() {
+        let allowlist = TrustAllowlist {
+            owners: vec!["rust-lang".to_string()],
+            ..Default::default()
+        };
+        let mut update_analyzer = get_test_update_analyzer();
+        update_analyzer.set_allowlist(allowlist);
+
+        let get_version_info = |version: &str, injected_file: Option<&str>| -> VersionInfo {
+            get_test_version_info("libc", version, injected_file)
+        };
+        let mut report = DepUpdateReviewReport {
+            name: "libc".to_string(),
+            prior_version: get_version_info("0.2.92", None),
+            updated_version: get_version_info("0.2.93", None),
+            diff_stats: None,
+            trusted: update_analyzer
+                .allowlist
+                .is_trusted("libc", Some("https://github.com/rust-lang/libc"))
+                .unwrap(),
+        };
+        assert!(report.trusted);
+        assert!(report.is_auto_passed());
+
+        // An injected file is reported even for a trusted crate
+        report.updated_version = get_version_info("0.2.93", Some("src/injected.rs"));
+        assert!(report.trusted);
+        assert!(report.has_hard_signals());
+        assert!(!report.is_auto_passed());
+
+        // So is a crate whose code could not be verified at all,
+        // e.g., without a repository, or without the release in it
+        let mut diff_analyzer = DiffAnalyzer::new().unwrap();
+        diff_analyzer.set_allow_local_repositories(true);
+        let vendor_dir = Path::new("resources/test/vendor");
+        let git_dir = tempdir().unwrap();
+        let git_repo = Repository::init(git_dir.path()).unwrap();
+        std::fs::write(git_dir.path().join("README.md"), "unrelated\n").unwrap();
+        let mut index = git_repo.index().unwrap();
+        index.add_path(Path::new("README.md")).unwrap();
+        let tree = git_repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("user", "email@domain.com").unwrap();
+        git_repo
+            .commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+            .unwrap();
+        let repository = Url::from_directory_path(git_dir.path())
+            .unwrap()
+            .to_string();
+        for (repository, verification_status) in &[
+            (None, VerificationStatus::NotAnalyzed),
+            (
+                Some(repository.as_str()),
+                VerificationStatus::CommitNotFound,
+            ),
+        ] {
+            let crate_source_diff_report = diff_analyzer
+                .analyze_vendored_source_diff(vendor_dir, "vendored-dep", "0.1.0", *repository)
+                .unwrap();
+            assert_eq!(
+                crate_source_diff_report.get_verification_status(),
+                *verification_status
+            );
+            report.updated_version.crate_source_diff_report = Some(crate_source_diff_report);
+            assert!(report.has_hard_signals());
+            assert!(!report.is_auto_passed());
+        }
+    }
+
+    #[test]
+    fn test_update_batch_summary() {
+        let mut with_unsafe = get_test_report("unsafe-crate", None);
+        let diff_stats = with_unsafe.diff_stats.as_mut().unwrap();
+        diff_stats.unsafe_file_changed = vec![FileUnsafeChangeStats {
+            file: "src/lib.rs".to_string(),
+            change_type: Delta::Modified,
+            unsafe_change_status: FileUnsafeCodeChangeStatus::UnsafeCounterModified,
+            unsafe_delta: UnsafeDelta {
+                expressions: 2,
+                ..Default::default()
+            },
+            unsafe_status: None,
+        }];
+        diff_stats.modified_build_scripts = vec!["build.rs".to_string()].into_iter().collect();
+
+        let mut with_unsafe_removed = get_test_report("safer-crate", None);
+        with_unsafe_removed
+            .diff_stats
+            .as_mut()
+            .unwrap()
+            .unsafe_file_changed = vec![FileUnsafeChangeStats {
+            file: "src/lib.rs".to_string(),
+            change_type: Delta::Modified,
+            unsafe_change_status: FileUnsafeCodeChangeStatus::UnsafeCounterModified,
+            unsafe_delta: UnsafeDelta {
+                functions: -1,
+                ..Default::default()
+            },
+            unsafe_status: None,
+        }];
+
+        let mut unanalyzed = get_test_report("no-source", None);
+        unanalyzed.updated_version.crate_source_diff_report = None;
+        unanalyzed.diff_stats = None;
+
+        let mut with_advisory = get_test_report("vulnerable", None);
+        with_advisory.trusted = true;
+        with_advisory
+            .updated_version
+            .known_advisories
+            .push(CrateVersionRustSecAdvisory {
+                id: "RUSTSEC-0000-0000".to_string(),
+                title: "test advisory".to_string(),
+                url: None,
+            });
+
+        let mut trusted = get_test_report("trusted", None);
+        trusted.trusted = true;
+
+        let mut near_match = get_test_report("near-match", Some("src/x.rs"));
+        if let Some(diff_report) = near_match.updated_version.crate_source_diff_report.as_mut() {
+            diff_report.is_near_match = true;
+        }
+
+        let summary = BatchSummary::from_reports(&[
+            get_test_report("injected", Some("src/x.rs")),
+            near_match,
+            with_unsafe,
+            with_unsafe_removed,
+            unanalyzed,
+            with_advisory,
+            trusted,
+        ]);
+
+        assert_eq!(summary.total, 7);
+        assert_eq!(summary.diverging, vec!["injected"]);
+        assert_eq!(summary.near_matches, vec!["near-match"]);
+        assert_eq!(summary.unanalyzed, vec!["no-source"]);
+        assert_eq!(summary.unsafe_additions, vec!["unsafe-crate"]);
+        assert_eq!(summary.known_advisories, vec!["vulnerable"]);
+        assert_eq!(summary.modified_build_scripts, vec!["unsafe-crate"]);
+        assert_eq!(summary.auto_passed, vec!["trusted"]);
+        assert_eq!(
+            summary.to_line(),
+            "7 updates: 1 diverging, 1 near matches, 1 unanalyzed, 1 with unsafe additions, \
+            1 with known advisories, 1 with modified build scripts, 1 auto-passed"
+        );
+    }
+
+    #[test]
+    fn test_update_has_findings() {
+        assert!(!get_test_report("clean", None).has_findings());
+
+        let mut yanked = get_test_report("yanked", None);
+        yanked.updated_version.yank_status = Some(VersionYankStatus {
+            is_yanked: true,
+            yank_reason: None,
+        });
+        assert!(yanked.has_findings());
+
+        let with_diff_stats = |update: &dyn Fn(&mut VersionDiffStats)| {
+            let mut report = get_test_report("changed", None);
+            update(report.diff_stats.as_mut().unwrap());
+            report
+        };
+        assert!(with_diff_stats(&|stats| {
+            stats.new_network_endpoints = vec![NetworkEndpoint {
+                endpoint: "https://collector.example.com".to_string(),
+                locations: vec!["src/lib.rs:3".to_string()],
+            }]
+        })
+        .has_findings());
+        assert!(with_diff_stats(&|stats| {
+            stats.new_secrets = vec![SecretFinding {
+                kind: "AWS access key id".to_string(),
+                locations: vec!["config/test.toml:1".to_string()],
+            }]
+        })
+        .has_findings());
+        assert!(with_diff_stats(&|stats| {
+            stats.default_features_changes = vec![DefaultFeaturesChange::DefaultFeature {
+                old: vec!["std".to_string()],
+                new: vec!["std".to_string(), "telemetry".to_string()],
+            }]
+        })
+        .has_findings());
+        assert!(with_diff_stats(&|stats| {
+            stats.msrv_change = Some((Some("1.46".to_string()), Some("1.56".to_string())));
+            stats.msrv_increased = true;
+        })
+        .has_findings());
+        // A lowered MSRV is not a finding
+        assert!(!with_diff_stats(&|stats| {
+            stats.msrv_change = Some((Some("1.56".to_string()), Some("1.46".to_string())));
+        })
+        .has_findings());
+
+        let with_diff_report = |update: &dyn Fn(&mut CrateSourceDiffReport)| {
+            let mut report = get_test_report("published", None);
+            update(
+                report
+                    .updated_version
+                    .crate_source_diff_report
+                    .as_mut()
+                    .unwrap(),
+            );
+            report
+        };
+        assert!(
+            with_diff_report(&|diff_report| diff_report.vcs_info_tag_mismatch = Some(true))
+                .has_findings()
+        );
+        assert!(
+            !with_diff_report(&|diff_report| diff_report.vcs_info_tag_mismatch = Some(false))
+                .has_findings()
+        );
+        assert!(with_diff_report(
+            &|diff_report| diff_report.license_declaration_mismatch = Some(true)
+        )
+        .has_findings());
+
+        // A differing source file weighs more than any other differing file
+        let mut injected = get_test_report("injected", Some("build/shim.c"));
+        assert_eq!(injected.get_risk_score(), 1);
+        injected
+            .updated_version
+            .crate_source_diff_report
+            .as_mut()
+            .unwrap()
+            .is_source_different = Some(true);
+        assert_eq!(injected.get_risk_score(), 2);
+    }
+
+    #[test]
+    fn test_update_retain_findings() {
+        let mut build_script_changed = get_test_report("build-changed", None);
+        if let Some(diff_stats) = build_script_changed.diff_stats.as_mut() {
+            diff_stats.modified_build_scripts = vec!["build.rs".to_string()].into_iter().collect();
+        }
+
+        // Differing within the benign lines threshold is still to review
+        let mut near_match = get_test_report("near-match", Some("src/x.rs"));
+        if let Some(diff_report) = near_match.updated_version.crate_source_diff_report.as_mut() {
+            diff_report.is_near_match = true;
+        }
+
+        let mut report = UpdateReviewReport {
+            dep_update_review_reports: vec![
+                get_test_report("clean-b", None),
+                get_test_report("injected", Some("src/x.rs")),
+                get_test_report("clean-a", None),
+                build_script_changed,
+                near_match,
+            ],
+            version_conflicts: Vec::new(),
+            introduced_dependencies: Vec::new(),
+            omitted_clean_crates: Vec::new(),
+        };
+        report.retain_findings();
+
+        let names: Vec<&str> = report
+            .dep_update_review_reports
+            .iter()
+            .map(|report| report.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["injected", "build-changed", "near-match"]);
+        assert_eq!(report.omitted_clean_crates, vec!["clean-a", "clean-b"]);
+
+        // The clean updates are still counted
+        let summary = report.get_batch_summary();
+        assert_eq!(summary.total, 5);
+        assert_eq!(summary.diverging, vec!["injected"]);
+        assert_eq!(summary.near_matches, vec!["near-match"]);
+
+        let markdown = crate::UpdateAnalyzer::get_markdown_report(&report)
+            .unwrap()
+            .unwrap();
+        assert!(markdown.contains("5 updates: 1 diverging, 1 near matches"));
+        assert!(markdown.contains("injected updated"));
+        assert!(!markdown.contains("clean-a updated"));
+        assert!(markdown.contains("2 clean updates omitted: clean-a, clean-b"));
+    }
+
+    #[test]
+    fn test_update_summarize() {
+        let get_summarized_report = |diff_stats: Option<VersionDiffStats>| DepUpdateReviewReport {
+            diff_stats,
+            ..get_test_report("summarized", None)
+        };
+        let clean_diff_stats = VersionDiffStats {
+            dependency_tree_unchanged: Some(true),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_summarized_report(Some(clean_diff_stats.clone())).summarize(),
+            "This update has no notable changes."
+        );
+
+        let mut diff_stats = clean_diff_stats.clone();
+        diff_stats.files_changed = ["build.rs", "src/a.rs", "src/b.rs", "src/lib.rs"]
+            .iter()
+            .map(|file| file.to_string())
+            .collect();
+        diff_stats.insertions = 120;
+        diff_stats.deletions = 30;
+        diff_stats.unsafe_file_changed = vec![FileUnsafeChangeStats {
+            file: "src/lib.rs".to_string(),
+            change_type: Delta::Modified,
+            unsafe_change_status: FileUnsafeCodeChangeStatus::UnsafeCounterModified,
+            unsafe_delta: UnsafeDelta {
+                expressions: 2,
+                functions: -1,
+                ..Default::default()
+            },
+            unsafe_status: None,
+        }];
+        diff_stats.modified_build_scripts = vec!["build.rs".to_string()].into_iter().collect();
+        assert_eq!(
+            get_summarized_report(Some(diff_stats)).summarize(),
+            "This update modifies 4 files (+120/-30), adds 2 unsafe usages, \
+            and changes the build script build.rs."
+        );
+
+        let mut diff_stats = clean_diff_stats;
+        diff_stats.msrv_change = Some((Some("1.56".to_string()), Some("1.60".to_string())));
+        diff_stats.msrv_increased = true;
+        let mut report = get_summarized_report(Some(diff_stats));
+        report
+            .updated_version
+            .known_advisories
+            .push(CrateVersionRustSecAdvisory {
+                id: "RUSTSEC-0000-0000".to_string(),
+                title: "test advisory".to_string(),
+                url: None,
+            });
+        assert_eq!(
+            report.summarize(),
+            "This update raises the minimum supported Rust version to 1.60 \
+            and has 1 known advisory."
+        );
+
+        let mut report = get_test_report("summarized", Some("src/x.rs"));
+        report.diff_stats = None;
+        assert_eq!(
+            report.summarize(),
+            "This update could not be diffed against the prior version \
+            and differs on crates.io from the git source."
+        );
+    }
+
+    #[test]
+    fn test_update_review_report_prometheus() {
+        let mut with_advisory = get_test_version_info("libc", "0.2.93", Some("src/x.rs"));
+        with_advisory
+            .known_advisories
+            .push(CrateVersionRustSecAdvisory {
+                id: "RUSTSEC-0000-0000".to_string(),
+                title: "test advisory".to_string(),
+                url: None,
+            });
+        let mut report = UpdateReviewReport {
+            dep_update_review_reports: vec![
+                DepUpdateReviewReport {
+                    name: "tokio".to_string(),
+                    prior_version: get_test_version_info("tokio", "1.7.1", None),
+                    updated_version: get_test_version_info("tokio", "1.7.2", None),
+                    diff_stats: Some(VersionDiffStats::default()),
+                    trusted: false,
+                },
+                DepUpdateReviewReport {
+                    name: "libc".to_string(),
+                    prior_version: get_test_version_info("libc", "0.2.92", None),
+                    updated_version: with_advisory,
+                    diff_stats: Some(VersionDiffStats::default()),
+                    trusted: false,
+                },
+            ],
+            version_conflicts: Vec::new(),
+            introduced_dependencies: Vec::new(),
+            omitted_clean_crates: Vec::new(),
+        };
+
+        let metrics = report.to_prometheus();
+        let lines: Vec<&str> = metrics.lines().collect();
+        for line in [
+            "# TYPE whackadep_crates_diverging gauge",
+            "whackadep_crates_total 2",
+            "whackadep_crates_diverging 1",
+            "whackadep_crates_near_matches 0",
+            "whackadep_crates_unanalyzable 0",
+            "whackadep_crate_findings{name=\"libc\",version=\"0.2.93\"} 1",
+            "whackadep_crate_findings{name=\"tokio\",version=\"1.7.2\"} 0",
+            "whackadep_crate_risk_score{name=\"libc\",version=\"0.2.93\"} 2",
+            "whackadep_crate_risk_score{name=\"tokio\",version=\"1.7.2\"} 0",
+            "whackadep_crate_known_advisories{name=\"libc\",version=\"0.2.93\"} 1",
+        ] {
+            assert!(lines.contains(&line), "missing {}", line);
+        }
+
+        // With --only-findings, the clean updates are still counted, but not labeled
+        report.retain_findings();
+        let metrics = report.to_prometheus();
+        let lines: Vec<&str> = metrics.lines().collect();
+        assert!(lines.contains(&"whackadep_crates_total 2"));
+        assert!(lines.contains(&"whackadep_crates_known_advisories 1"));
+        assert!(!metrics.contains("name=\"tokio\""));
+        assert_eq!(escape_label_value("a\"b\\c"), "a\\\"b\\\\c");
+    }
+
+    #[test]
+    fn test_update_review_report_table() {
+        let long_name = "a-crate-with-a-name-too-long-for-a-table";
+        let report = UpdateReviewReport {
+            dep_update_review_reports: vec![
+                DepUpdateReviewReport {
+                    name: "tokio".to_string(),
+                    prior_version: get_test_version_info("tokio", "1.7.1", None),
+                    updated_version: get_test_version_info("tokio", "1.7.2", None),
+                    diff_stats: Some(VersionDiffStats::default()),
+                    trusted: true,
+                },
+                DepUpdateReviewReport {
+                    name: "libc".to_string(),
+                    prior_version: get_test_version_info("libc", "0.2.92", None),
+                    updated_version: get_test_version_info("libc", "0.2.93", Some("src/x.rs")),
+                    diff_stats: Some(VersionDiffStats {
+                        msrv_increased: true,
+                        public_api_changed: true,
+                        ..Default::default()
+                    }),
+                    trusted: false,
+                },
+                DepUpdateReviewReport {
+                    name: long_name.to_string(),
+                    prior_version: get_test_version_info(long_name, "0.1.0", None),
+                    updated_version: VersionInfo {
+                        crate_source_diff_report: None,
+                        ..get_test_version_info(long_name, "1.0.0", None)
+                    },
+                    diff_stats: None,
+                    trusted: false,
+                },
+            ],
+            version_conflicts: Vec::new(),
+            introduced_dependencies: Vec::new(),
+            omitted_clean_crates: Vec::new(),
+        };
+
+        assert_eq!(
+            report.to_table(),
+            "CRATE                     UPDATE            VERIFICATION  FINDINGS  FLAGS\n\
+             a-crate-with-a-name-too…  0.1.0 -> 1.0.0    -             yes       -\n\
+             libc                      0.2.92 -> 0.2.93  Differs       yes       msrv,api\n\
+             tokio                     1.7.1 -> 1.7.2    Matches       no        auto-passed\n"
+        );
+    }
+
+    #[test]
+    fn test_update_review_report_github_annotations() {
+        let diff_stats = VersionDiffStats {
+            unsafe_file_changed: vec![
+                FileUnsafeChangeStats {
+                    file: "src/unix.rs".to_string(),
+                    change_type: Delta::Modified,
+                    unsafe_change_status: FileUnsafeCodeChangeStatus::UnsafeCounterModified,
+                    unsafe_delta: UnsafeDelta {
+                        functions: 1,
+                        ..Default::default()
+                    },
+                    unsafe_status: None,
+                },
+                FileUnsafeChangeStats {
+                    file: "src/removed.rs".to_string(),
+                    change_type: Delta::Modified,
+                    unsafe_change_status: FileUnsafeCodeChangeStatus::UnsafeCounterModified,
+                    unsafe_delta: UnsafeDelta {
+                        functions: -1,
+                        ..Default::default()
+                    },
+                    unsafe_status: None,
+                },
+            ],
+            new_secrets: vec![SecretFinding {
+                kind: "GitHub token".to_string(),
+                locations: vec!["src/auth.rs:12".to_string()],
+            }],
+            ..Default::default()
+        };
+
+        let mut updated_version = get_test_version_info("libc", "0.2.93", Some("src/x,y.rs"));
+        if let Some(diff_report) = updated_version.crate_source_diff_report.as_mut() {
+            diff_report.published_ignored_files = vec!["target/libc.so".to_string()];
+        }
+        let report = UpdateReviewReport {
+            dep_update_review_reports: vec![
+                DepUpdateReviewReport {
+                    name: "tokio".to_string(),
+                    prior_version: get_test_version_info("tokio", "1.7.1", None),
+                    updated_version: get_test_version_info("tokio", "1.7.2", None),
+                    diff_stats: None,
+                    trusted: false,
+                },
+                DepUpdateReviewReport {
+                    name: "libc".to_string(),
+                    prior_version: get_test_version_info("libc", "0.2.92", None),
+                    updated_version,
+                    diff_stats: Some(diff_stats),
+                    trusted: false,
+                },
+            ],
+            version_conflicts: Vec::new(),
+            introduced_dependencies: Vec::new(),
+            omitted_clean_crates: Vec::new(),
+        };
+
+        let annotations = report.to_github_annotations();
+        let lines: Vec<&str> = annotations.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "::error title=libc 0.2.93::src/x,y.rs: file in crates.io is not present in the git source",
+                "::warning title=libc 0.2.93::target/libc.so: published file is excluded by the .gitignore of the git source",
+                "::warning title=libc 0.2.93::src/unix.rs: unsafe code added in the update from 0.2.92",
+                "::error title=libc 0.2.93::src/auth.rs:12: likely GitHub token added in the update",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_diff_batch_reports() {
+        let get_advised_report = |name: &str, injected_file: Option<&str>, advisories: &[&str]| {
+            let mut report = get_test_report(name, injected_file);
+            report.updated_version.known_advisories = advisories
+                .iter()
+                .map(|id| CrateVersionRustSecAdvisory {
+                    id: id.to_string(),
+                    title: "advisory".to_string(),
+                    url: None,
+                })
+                .collect();
+            report
+        };
+
+        let get_scored_report = |name: &str, score: Option<f64>| PackageOSSFReport {
+            name: name.to_string(),
+            version: "0.1.1".to_string(),
+            repository: Some(format!("https://github.com/owner/{}", name)),
+            ossf_report: Some(OSSFReport {
+                date: "2021-07-19".to_string(),
+                repo: OSSFRepo {
+                    name: format!("github.com/owner/{}", name),
+                    commit: None,
+                },
+                score,
+                checks: Vec::new(),
+            }),
+        };
+
+        let old = BatchReport {
+            reviews: vec![
+                get_test_report("steady", None),
+                get_test_report("diverges", None),
+                get_test_report("converges", Some("target/x.so")),
+                get_advised_report("advised", None, &["RUSTSEC-2021-0001"]),
+                get_test_report("dropped", None),
+            ],
+            ossf_reports: vec![
+                get_scored_report("steady", Some(7.5)),
+                get_scored_report("diverges", Some(6.0)),
+                get_scored_report("converges", Some(5.0)),
+                get_scored_report("unscored", None),
+            ],
+        };
+        let new = BatchReport {
+            reviews: vec![
+                get_test_report("steady", None),
+                get_test_report("diverges", Some("src/x.rs")),
+                get_test_report("converges", None),
+                get_advised_report("advised", None, &["RUSTSEC-2021-0001", "RUSTSEC-2021-0002"]),
+                get_advised_report("introduced", Some("build.rs"), &["RUSTSEC-2021-0003"]),
+            ],
+            ossf_reports: vec![
+                get_scored_report("steady", Some(7.5)),
+                get_scored_report("diverges", Some(4.5)),
+                get_scored_report("converges", Some(5.5)),
+                get_scored_report("unscored", Some(3.0)),
+                get_scored_report("introduced", Some(2.0)),
+            ],
+        };
+
+        let delta = diff_batch_reports(&old, &new);
+        assert_eq!(delta.added, vec!["introduced"]);
+        assert_eq!(delta.removed, vec!["dropped"]);
+        assert_eq!(delta.newly_diverging, vec!["diverges", "introduced"]);
+        assert_eq!(delta.no_longer_diverging, vec!["converges"]);
+        assert_eq!(
+            delta.new_advisories,
+            vec![
+                ("advised".to_string(), "RUSTSEC-2021-0002".to_string()),
+                ("introduced".to_string(), "RUSTSEC-2021-0003".to_string()),
+            ]
+        );
+
+        // a crate newly scored, or unscored before, has no drop
+        assert_eq!(
+            delta.ossf_score_drops,
+            vec![("diverges".to_string(), 6.0, 4.5)]
+        );
+
+        assert!(diff_batch_reports(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_update_review_report_ndjson() {
+        let diff_stats = VersionDiffStats {
+            files_changed: vec!["src/lib.rs".to_string()].into_iter().collect(),
+            rust_files_changed: 1,
+            insertions: 1,
+            deletions: 0,
+            modified_build_scripts: HashSet::new(),
+            unsafe_file_changed: vec![FileUnsafeChangeStats {
+                file: "src/lib.rs".to_string(),
+                change_type: Delta::Modified,
+                unsafe_change_status: FileUnsafeCodeChangeStatus::UnsafeCounterModified,
+                unsafe_delta: UnsafeDelta {
+                    functions: 1,
+                    ..Default::default()
+                },
+                unsafe_status: None,
+            }],
+            package_renamed_from: None,
+            largest_file_changes: Vec::new(),
+            msrv_change: Some((None, Some("1.56".to_string()))),
+            msrv_increased: true,
+            directive_changes: Vec::new(),
+            public_api_changed: false,
+            public_api_changes: Vec::new(),
+            macro_changes: Vec::new(),
+            non_substantive_changes: Vec::new(),
+            untested_changed_files: Vec::new(),
+            dependency_tree_unchanged: Some(true),
+            added_dependencies: Vec::new(),
+            changed_rust_modules: vec![("src/lib.rs".to_string(), "crate".to_string())],
+            default_features_changes: Vec::new(),
+            new_network_endpoints: Vec::new(),
+            new_secrets: Vec::new(),
+            custom_findings: Vec::new(),
+        };
+        let report = UpdateReviewReport {
+            dep_update_review_reports: vec![
+                DepUpdateReviewReport {
+                    name: "tokio".to_string(),
+                    prior_version: get_test_version_info("tokio", "1.7.1", None),
+                    updated_version: get_test_version_info("tokio", "1.7.2", None),
+                    diff_stats: Some(diff_stats),
+                    trusted: false,
+                },
+                DepUpdateReviewReport {
+                    name: "libc".to_string(),
+                    prior_version: get_test_version_info("libc", "0.2.92", None),
+                    updated_version: get_test_version_info("libc", "0.2.93", Some("src/x.rs")),
+                    diff_stats: None,
+                    trusted: true,
+                },
+            ],
+            version_conflicts: Vec::new(),
+            introduced_dependencies: Vec::new(),
+            omitted_clean_crates: Vec::new(),
+        };
+
+        let ndjson = report.to_ndjson().unwrap();
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let reports: Vec<DepUpdateReviewReport> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(reports[0].name, "libc");
+        assert!(reports[0].has_hard_signals());
+        // the status is in the json, not only computed from it
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(
+            line["updated_version"]["crate_source_diff_report"]["verification_status"],
+            "Differs"
+        );
+        assert_eq!(reports[1].name, "tokio");
+        assert_eq!(
+            reports[1]
+                .updated_version
+                .crate_source_diff_report
+                .as_ref()
+                .unwrap()
+                .get_verification_status(),
+            VerificationStatus::Matches
+        );
+        let unsafe_file_changed = &reports[1].diff_stats.as_ref().unwrap().unsafe_file_changed;
+        assert_eq!(unsafe_file_changed[0].change_type, Delta::Modified);
+        assert_eq!(unsafe_file_changed[0].unsafe_delta.functions, 1);
+    }
+
+    #[test]
+    fn test_update_get_manifest_path_from_graph() {
+        let package_graph_pair = get_test_graph_pair_guppy();
+
+        // syn is in the graph at both 0.15 and 1.0
+        for version in &["0.15.44", "1.0.73"] {
+            let manifest_path = UpdateAnalyzer::get_manifest_path_from_graph(
+                &package_graph_pair.post,
+                "syn",
+                &Version::parse(version).unwrap(),
+            )
+            .unwrap();
+            assert!(manifest_path.ends_with(format!("syn-{}/Cargo.toml", version)));
+        }
+        assert!(UpdateAnalyzer::get_manifest_path_from_graph(
+            &package_graph_pair.post,
+            "syn",
+            &Version::parse("2.0.0").unwrap(),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_update_docsrs_report_offline() {
+        let update_analyzer = get_test_update_analyzer();
+        let dir = tempdir().unwrap();
+        let manifest_path =
+            camino::Utf8PathBuf::from_path_buf(dir.path().join("Cargo.toml")).unwrap();
+        let version = Version::parse("0.1.0").unwrap();
+        let enabled_features = vec!["std".to_string(), "unstable".to_string()];
+
+        // an unparsable manifest is not fatal
+        std::fs::write(&manifest_path, "[package\n").unwrap();
+        assert!(update_analyzer
+            .get_docsrs_report("a", &version, &manifest_path, &enabled_features, true)
+            .is_none());
+
+        // the undocumented features are checked without docs.rs
+        std::fs::write(
+            &manifest_path,
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n\
+             [features]\ndefault = [\"std\"]\nstd = []\nunstable = []\n",
+        )
+        .unwrap();
+        let docsrs_report = update_analyzer
+            .get_docsrs_report("a", &version, &manifest_path, &enabled_features, true)
+            .unwrap();
+        assert_eq!(docsrs_report.build_succeeded, None);
+        assert_eq!(docsrs_report.undocumented_features, vec!["unstable"]);
     }
 
     #[test]